@@ -0,0 +1,66 @@
+use std::{fs, io, path::Path};
+
+use crate::{graphics::Canvas, image::canvas_to_ppm};
+
+pub fn render_frames<F>(count: usize, frame_fn: F) -> Vec<Canvas>
+where
+    F: FnMut(usize) -> Canvas,
+{
+    (0..count).map(frame_fn).collect()
+}
+
+/// Writes each frame to `dir` as `<prefix>_0000.ppm`, `<prefix>_0001.ppm`, etc.
+pub fn write_frames_ppm(frames: &[Canvas], dir: &Path, prefix: &str) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    frames.iter().enumerate().try_for_each(|(i, frame)| {
+        fs::write(
+            dir.join(format!("{}_{:04}.ppm", prefix, i)),
+            canvas_to_ppm(frame),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphics::Color;
+
+    use super::*;
+
+    #[test]
+    fn test_render_frames() {
+        let mut calls = vec![];
+        let frames = render_frames(3, |i| {
+            calls.push(i);
+            let mut c = Canvas::new(4, 2);
+            c.write_px(i, 0, Color::WHITE);
+            c
+        });
+
+        assert_eq!(calls, vec![0, 1, 2]);
+        assert_eq!(frames.len(), 3);
+        frames.iter().enumerate().for_each(|(i, frame)| {
+            assert_eq!(frame.w(), 4);
+            assert_eq!(frame.h(), 2);
+            assert_eq!(frame.px(i, 0), Color::WHITE);
+        });
+    }
+
+    #[test]
+    fn test_write_frames_ppm() {
+        let dir = std::env::temp_dir().join(format!("rtc_frames_{}", std::process::id()));
+        let frames = render_frames(2, |_| Canvas::new(3, 2));
+
+        write_frames_ppm(&frames, &dir, "clock").unwrap();
+
+        ["clock_0000.ppm", "clock_0001.ppm"]
+            .into_iter()
+            .for_each(|name| {
+                let ppm = fs::read_to_string(dir.join(name)).unwrap();
+                assert_eq!(ppm, canvas_to_ppm(&frames[0]));
+            });
+        assert!(!dir.join("clock_0002.ppm").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,4 +1,4 @@
-use std::fs;
+use std::path::Path;
 
 use the_ray_tracer_challenge::{
     graphics::{Canvas, Color},
@@ -59,6 +59,6 @@ fn main() {
         p = tick(&e, &p);
     }
 
-    let ppm = image::canvas_to_ppm(&canvas);
-    fs::write("./ch02_cannon_pic.ppm", ppm).expect("Cannot write image to file!");
+    image::write_image(&canvas, Path::new("./ch02_cannon_pic.ppm"))
+        .expect("Cannot write image to file!");
 }
@@ -1,4 +1,4 @@
-use the_ray_tracer_challenge::math::{assert_float_eq, Matrix4x4f};
+use the_ray_tracer_challenge::math::{assert_float_eq, assert_float_eq_within_msg, Matrix4x4f};
 
 fn main() {
     fn loose_compare_matrix4x4f(left: &Matrix4x4f, right: &Matrix4x4f) {
@@ -6,15 +6,11 @@ fn main() {
 
         (0..4).for_each(|r| {
             (0..4).for_each(|c| {
-                let left = left.get(r, c);
-                let right = right.get(r, c);
-                assert!(
-                    (left - right).abs() < ACCEPTABLE_DELTA,
-                    "{} != {}, Cell: ({}, {})",
-                    left,
-                    right,
-                    r,
-                    c
+                assert_float_eq_within_msg(
+                    left.get(r, c),
+                    right.get(r, c),
+                    ACCEPTABLE_DELTA,
+                    &format!("Cell: ({}, {})", r, c),
                 );
             })
         });
@@ -1,4 +1,4 @@
-use std::{f64::consts::PI, fs};
+use std::{f64::consts::PI, path::Path};
 use the_ray_tracer_challenge::{
     graphics::{Canvas, Color},
     image,
@@ -20,6 +20,6 @@ fn main() {
         canvas.write_px(p.x().round() as usize, p.y().round() as usize, color);
     });
 
-    let ppm = image::canvas_to_ppm(&canvas);
-    fs::write("./ch04_clock.ppm", ppm).expect("Cannot write image to file!");
+    image::write_image(&canvas, Path::new("./ch04_clock.ppm"))
+        .expect("Cannot write image to file!");
 }
@@ -1,4 +1,4 @@
-use std::fs;
+use std::path::Path;
 
 use the_ray_tracer_challenge::{
     geometry::{Ray, Sphere},
@@ -112,6 +112,6 @@ fn main() {
         Color::new(0.0, 1.0, 1.0),
     );
 
-    let ppm = image::canvas_to_ppm(&canvas);
-    fs::write("./ch05_circle.ppm", ppm).expect("Cannot write image to file!");
+    image::write_image(&canvas, Path::new("./ch05_circle.ppm"))
+        .expect("Cannot write image to file!");
 }
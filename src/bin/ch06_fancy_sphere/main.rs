@@ -1,4 +1,4 @@
-use std::fs;
+use std::path::Path;
 
 use the_ray_tracer_challenge::{
     geometry::{Ray, Sphere},
@@ -40,14 +40,15 @@ fn draw_scenario(
                     .hit()
                     .map(|hit| {
                         let point = ray.position(hit.t());
-                        let normal = sphere.normal_at(&point);
+                        let normal = hit.get_object().normal_at(&point);
                         let eye = -ray.get_direction();
                         lighting(LightingArgs {
-                            material: sphere.get_material(),
-                            light,
+                            material: hit.get_object().material(),
+                            light: light.into(),
                             point,
                             eyev: eye,
                             normalv: normal,
+                            light_intensity: 1.0,
                         })
                     })
                     .unwrap_or(wall_color),
@@ -159,6 +160,6 @@ fn main() {
         ),
     );
 
-    let ppm = image::canvas_to_ppm(&canvas);
-    fs::write("./ch06_fancy_sphere.ppm", ppm).expect("Cannot write image to file!");
+    image::write_image(&canvas, Path::new("./ch06_fancy_sphere.ppm"))
+        .expect("Cannot write image to file!");
 }
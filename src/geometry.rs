@@ -1,5 +1,7 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use crate::{
-    math::{Matrix4x4f, Point3f, Vector3f, Vector4f},
+    math::{FloatEq, Matrix4x4f, Point3f, Vector3f},
     shading::Material,
 };
 
@@ -7,11 +9,27 @@ use crate::{
 pub struct Ray {
     origin: Point3f,
     direction: Vector3f,
+    // intersections further than this are ignored
+    max_t: f64,
 }
 
 impl Ray {
     pub fn new(origin: Point3f, direction: Vector3f) -> Self {
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            max_t: f64::INFINITY,
+        }
+    }
+
+    /// Same ray, but intersections with `t > max_t` are ignored (e.g. a shadow ray that
+    /// stops at the light)
+    pub fn with_max_t(self, max_t: f64) -> Self {
+        Self { max_t, ..self }
+    }
+
+    pub fn get_max_t(&self) -> f64 {
+        self.max_t
     }
 
     pub fn get_origin(&self) -> Point3f {
@@ -27,7 +45,16 @@ impl Ray {
     }
 
     pub fn intersect_sphere<'a>(&self, sphere: &'a Sphere) -> Intersections<'a> {
-        let transformed_ray = self.transform(&sphere.transform.inverse().unwrap());
+        let mut buf = vec![];
+        self.intersect_sphere_into(sphere, &mut buf);
+        Intersections::new(buf)
+    }
+
+    /// Same as `intersect_sphere()`, but appends the (unsorted) intersections to a
+    /// caller-owned buffer instead of allocating a new list. Call `sort_intersections()`
+    /// once after intersecting every object, and clear the buffer before reusing it.
+    pub fn intersect_sphere_into<'a>(&self, sphere: &'a Sphere, buf: &mut Vec<Intersection<'a>>) {
+        let transformed_ray = self.transform(&inverse_transform(&sphere.transform));
         let sphere_to_ray = transformed_ray.origin - Point3f::new(0.0, 0.0, 0.0);
 
         let a = transformed_ray.direction.dot(&transformed_ray.direction);
@@ -36,29 +63,136 @@ impl Ray {
 
         let discriminant = (b * b) - (4.0 * a * c);
 
-        if discriminant < 0.0 {
-            Intersections::new_empty()
-        } else {
+        if discriminant >= 0.0 {
             let first = (-b - discriminant.sqrt()) / (2.0 * a);
             let second = (-b + discriminant.sqrt()) / (2.0 * a);
 
-            Intersections::new(vec![
-                Intersection::new(first, IntersectionObject::Sphere(sphere)),
-                Intersection::new(second, IntersectionObject::Sphere(sphere)),
-            ])
+            [first, second]
+                .into_iter()
+                .filter(|t| *t <= self.max_t)
+                .for_each(|t| buf.push(Intersection::new(t, IntersectionObject::Sphere(sphere))));
+        }
+    }
+
+    /// Returns (tmin, tmax) of where the ray enters and exits the box, or `None` if the
+    /// ray (extended in both directions) misses it. Both can be negative if the box is
+    /// behind the ray.
+    pub fn intersect_box(&self, b: &BoundingBox) -> Option<(f64, f64)> {
+        fn check_axis(origin: f64, direction: f64, min: f64, max: f64) -> (f64, f64) {
+            if direction.abs() >= f64::EPSILON {
+                let t1 = (min - origin) / direction;
+                let t2 = (max - origin) / direction;
+                (t1.min(t2), t1.max(t2))
+            } else if origin >= min && origin <= max {
+                // parallel to the slab, and inside it
+                (f64::NEG_INFINITY, f64::INFINITY)
+            } else {
+                // parallel to the slab, but outside of it
+                (f64::INFINITY, f64::NEG_INFINITY)
+            }
+        }
+
+        let (o, d, min, max) = (self.origin, self.direction, b.get_min(), b.get_max());
+        let (x_min, x_max) = check_axis(o.x(), d.x(), min.x(), max.x());
+        let (y_min, y_max) = check_axis(o.y(), d.y(), min.y(), max.y());
+        let (z_min, z_max) = check_axis(o.z(), d.z(), min.z(), max.z());
+
+        let tmin = x_min.max(y_min).max(z_min);
+        let tmax = x_max.min(y_max).min(z_max);
+
+        if tmin > tmax {
+            None
+        } else {
+            Some((tmin, tmax))
+        }
+    }
+
+    /// Whether the ray hits the box in front of its origin (or starts inside it), and
+    /// before its `max_t`
+    pub fn intersects_box(&self, b: &BoundingBox) -> bool {
+        self.intersect_box(b)
+            .is_some_and(|(tmin, tmax)| tmax >= 0.0 && tmin <= self.max_t)
+    }
+
+    /// Ray that starts at `point` and travels in the direction of this ray after
+    /// bouncing off a surface with the given normal
+    pub fn reflect_at(&self, point: Point3f, normal: &Vector3f) -> Self {
+        Self::new(point, self.direction.reflect(normal))
+    }
+
+    pub fn intersect<'a>(&self, shape: &'a ShapeKind) -> Intersections<'a> {
+        let mut buf = vec![];
+        self.intersect_into(shape, &mut buf);
+        Intersections::new(buf)
+    }
+
+    /// Same as `intersect_sphere_into()`, but for any kind of shape
+    pub fn intersect_into<'a>(&self, shape: &'a ShapeKind, buf: &mut Vec<Intersection<'a>>) {
+        match shape {
+            ShapeKind::Sphere(sphere) => self.intersect_sphere_into(sphere, buf),
+            ShapeKind::Disc(disc) => self.intersect_disc_into(disc, buf),
+        }
+    }
+
+    pub fn intersect_disc<'a>(&self, disc: &'a Disc) -> Intersections<'a> {
+        let mut buf = vec![];
+        self.intersect_disc_into(disc, &mut buf);
+        Intersections::new(buf)
+    }
+
+    /// Same as `intersect_sphere_into()`, but for a disc
+    pub fn intersect_disc_into<'a>(&self, disc: &'a Disc, buf: &mut Vec<Intersection<'a>>) {
+        let transformed_ray = self.transform(&inverse_transform(&disc.transform));
+
+        // parallel to the disc, or lies within it (which we treat as a miss)
+        if transformed_ray.direction.y().float_eq(&0.0) {
+            return;
+        }
+
+        let t = -transformed_ray.origin.y() / transformed_ray.direction.y();
+        let point = transformed_ray.position(t);
+        let dist_squared = point.x() * point.x() + point.z() * point.z();
+
+        if t <= self.max_t
+            && dist_squared <= disc.radius * disc.radius
+            && dist_squared >= disc.inner_radius * disc.inner_radius
+        {
+            buf.push(Intersection::new(t, IntersectionObject::Disc(disc)));
         }
     }
 
     pub fn transform(&self, matrix: &Matrix4x4f) -> Self {
+        // the direction is not normalized, so t (and max_t) stays the same
         Self {
             origin: *matrix * self.origin,
             direction: *matrix * self.direction,
+            max_t: self.max_t,
         }
     }
 }
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+/// Skips the (comparatively expensive) inverse for the common untransformed shape
+fn inverse_transform(transform: &Matrix4x4f) -> Matrix4x4f {
+    if transform.is_identity() {
+        Matrix4x4f::identity()
+    } else {
+        transform.inverse().unwrap()
+    }
+}
+
+fn next_shape_id() -> usize {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Every constructed sphere gets a unique id. Copies of a sphere keep the id of the
+/// original, i.e. they are considered to be the same object.
+///
+/// Equality compares the transform and material (within floating point tolerance) and
+/// ignores the id, use `id()` to check whether two spheres are the same object.
+#[derive(Debug, Copy, Clone)]
 pub struct Sphere {
+    id: usize,
     transform: Matrix4x4f,
     material: Material,
 }
@@ -66,25 +200,52 @@ pub struct Sphere {
 impl Sphere {
     pub fn new(transform: Matrix4x4f, material: Material) -> Self {
         Self {
+            id: next_shape_id(),
             transform,
             material,
         }
     }
 
+    /// Unit sphere made of glass
+    pub fn glass() -> Self {
+        Self::new(Matrix4x4f::identity(), Material::glass())
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn get_transform(&self) -> Matrix4x4f {
+        self.transform
+    }
+
     pub fn set_transform(&mut self, transform: Matrix4x4f) {
         self.transform = transform;
     }
 
+    pub fn with_transform(mut self, transform: Matrix4x4f) -> Self {
+        self.set_transform(transform);
+        self
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.set_material(material);
+        self
+    }
+
     pub fn normal_at(&self, world_point: &Point3f) -> Vector3f {
-        let world_point: Vector4f = (*world_point).into();
-        let object_origin: Vector4f = (Point3f::new(0.0, 0.0, 0.0)).into();
-
-        let object_point = self.transform.inverse().unwrap() * world_point;
-        let object_normal = object_point - object_origin;
-        let world_normal = self.transform.inverse().unwrap().transpose() * object_normal;
-        // hack, see page 82. Techincally we should remove all manipulation of w in the transposed
-        // inversed matrix, but we can also just reset w to 0 (i.e. make it a vector)
-        let world_normal = Vector3f::new(world_normal.x(), world_normal.y(), world_normal.z());
+        let inverse = inverse_transform(&self.transform);
+
+        let object_point = inverse * *world_point;
+        // the center has no normal of its own, so just pick one instead of dividing by 0
+        let object_normal = (object_point - Point3f::new(0.0, 0.0, 0.0))
+            .try_normalize()
+            .unwrap_or(Vector3f::new(0.0, 1.0, 0.0));
+        let world_normal = inverse.transpose() * object_normal;
         world_normal.normalize()
     }
 
@@ -93,13 +254,223 @@ impl Sphere {
     }
 }
 
+impl PartialEq for Sphere {
+    fn eq(&self, other: &Self) -> bool {
+        self.transform.float_eq(&other.transform) && self.material.float_eq(&other.material)
+    }
+}
+
 impl Default for Sphere {
     fn default() -> Self {
-        Sphere {
-            transform: Matrix4x4f::identity(),
-            material: Material::default(),
+        Sphere::new(Matrix4x4f::identity(), Material::default())
+    }
+}
+
+/// Flat circle on the xz plane, centered at the origin. With a non-zero inner radius,
+/// it becomes a ring (annulus).
+///
+/// Same id and equality semantics as `Sphere`.
+#[derive(Debug, Copy, Clone)]
+pub struct Disc {
+    id: usize,
+    transform: Matrix4x4f,
+    material: Material,
+    radius: f64,
+    inner_radius: f64,
+}
+
+impl Disc {
+    pub fn new(transform: Matrix4x4f, material: Material, radius: f64, inner_radius: f64) -> Self {
+        Self {
+            id: next_shape_id(),
+            transform,
+            material,
+            radius,
+            inner_radius,
         }
     }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn get_transform(&self) -> Matrix4x4f {
+        self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix4x4f) {
+        self.transform = transform;
+    }
+
+    pub fn with_transform(mut self, transform: Matrix4x4f) -> Self {
+        self.set_transform(transform);
+        self
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.set_material(material);
+        self
+    }
+
+    pub fn normal_at(&self, _world_point: &Point3f) -> Vector3f {
+        let object_normal = Vector3f::new(0.0, 1.0, 0.0);
+        let world_normal = inverse_transform(&self.transform).transpose() * object_normal;
+        world_normal.normalize()
+    }
+
+    pub fn get_material(&self) -> Material {
+        self.material
+    }
+}
+
+impl PartialEq for Disc {
+    fn eq(&self, other: &Self) -> bool {
+        self.transform.float_eq(&other.transform)
+            && self.material.float_eq(&other.material)
+            && self.radius.float_eq(&other.radius)
+            && self.inner_radius.float_eq(&other.inner_radius)
+    }
+}
+
+impl Default for Disc {
+    fn default() -> Self {
+        Disc::new(Matrix4x4f::identity(), Material::default(), 1.0, 0.0)
+    }
+}
+
+/// Owned shape of any kind, so that different shapes can be stored together (e.g. in
+/// a `Vec`) without boxing them
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum ShapeKind {
+    Sphere(Sphere),
+    Disc(Disc),
+}
+
+impl ShapeKind {
+    pub fn as_object(&self) -> IntersectionObject<'_> {
+        match self {
+            ShapeKind::Sphere(sphere) => IntersectionObject::Sphere(sphere),
+            ShapeKind::Disc(disc) => IntersectionObject::Disc(disc),
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.as_object().id()
+    }
+
+    pub fn material(&self) -> Material {
+        self.as_object().material()
+    }
+
+    pub fn transform(&self) -> Matrix4x4f {
+        self.as_object().transform()
+    }
+
+    pub fn normal_at(&self, world_point: &Point3f) -> Vector3f {
+        self.as_object().normal_at(world_point)
+    }
+
+    /// Whether the object with the given id is this shape or part of it. Every shape
+    /// is a leaf for now, so this is only true for the shape itself.
+    pub fn includes(&self, id: usize) -> bool {
+        self.id() == id
+    }
+}
+
+impl From<Sphere> for ShapeKind {
+    fn from(value: Sphere) -> Self {
+        ShapeKind::Sphere(value)
+    }
+}
+
+impl From<Disc> for ShapeKind {
+    fn from(value: Disc) -> Self {
+        ShapeKind::Disc(value)
+    }
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+/// Axis-aligned bounding box
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct BoundingBox {
+    min: Point3f,
+    max: Point3f,
+}
+
+impl BoundingBox {
+    pub fn new(min: Point3f, max: Point3f) -> Self {
+        Self { min, max }
+    }
+
+    pub fn get_min(&self) -> Point3f {
+        self.min
+    }
+
+    pub fn get_max(&self) -> Point3f {
+        self.max
+    }
+
+    /// If there are multiple longest axes, x is preferred over y, and y over z
+    pub fn longest_axis(&self) -> Axis {
+        let size = self.max - self.min;
+
+        if size.x() >= size.y() && size.x() >= size.z() {
+            Axis::X
+        } else if size.y() >= size.z() {
+            Axis::Y
+        } else {
+            Axis::Z
+        }
+    }
+
+    /// Surface area of the box
+    pub fn area(&self) -> f64 {
+        let size = self.max - self.min;
+        2.0 * (size.x() * size.y() + size.y() * size.z() + size.z() * size.x())
+    }
+
+    /// Splits the box into two halves along its longest axis
+    pub fn split_bounds(&self) -> (BoundingBox, BoundingBox) {
+        let (min, max) = (self.min, self.max);
+        let (mid_min, mid_max) = match self.longest_axis() {
+            Axis::X => {
+                let mid = (min.x() + max.x()) / 2.0;
+                (
+                    Point3f::new(mid, min.y(), min.z()),
+                    Point3f::new(mid, max.y(), max.z()),
+                )
+            }
+            Axis::Y => {
+                let mid = (min.y() + max.y()) / 2.0;
+                (
+                    Point3f::new(min.x(), mid, min.z()),
+                    Point3f::new(max.x(), mid, max.z()),
+                )
+            }
+            Axis::Z => {
+                let mid = (min.z() + max.z()) / 2.0;
+                (
+                    Point3f::new(min.x(), min.y(), mid),
+                    Point3f::new(max.x(), max.y(), mid),
+                )
+            }
+        };
+
+        (
+            BoundingBox::new(min, mid_max),
+            BoundingBox::new(mid_min, max),
+        )
+    }
 }
 
 #[derive(PartialEq, Debug, Copy, Clone)]
@@ -120,19 +491,67 @@ impl<'a> Intersection<'a> {
     pub fn get_object(&self) -> &IntersectionObject<'a> {
         &self.object
     }
+
+    /// Orders by t. NaN can show up on degenerate rays, it is treated as greater than
+    /// everything so that it ends up at the back of a sorted list and never gets picked
+    /// as a hit.
+    pub fn cmp_by_t(&self, other: &Intersection) -> std::cmp::Ordering {
+        match (self.t.is_nan(), other.t.is_nan()) {
+            (false, false) => self.t.partial_cmp(&other.t).unwrap(),
+            (true, false) => std::cmp::Ordering::Greater,
+            (false, true) => std::cmp::Ordering::Less,
+            (true, true) => std::cmp::Ordering::Equal,
+        }
+    }
 }
 
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum IntersectionObject<'a> {
     Sphere(&'a Sphere),
+    Disc(&'a Disc),
+}
+
+impl IntersectionObject<'_> {
+    pub fn id(&self) -> usize {
+        match self {
+            IntersectionObject::Sphere(sphere) => sphere.id(),
+            IntersectionObject::Disc(disc) => disc.id(),
+        }
+    }
+
+    /// Whether both refer to the same object (not just an object with equal fields)
+    pub fn is_same_object(&self, other: &IntersectionObject) -> bool {
+        self.id() == other.id()
+    }
+
+    pub fn material(&self) -> Material {
+        match self {
+            IntersectionObject::Sphere(sphere) => sphere.get_material(),
+            IntersectionObject::Disc(disc) => disc.get_material(),
+        }
+    }
+
+    pub fn transform(&self) -> Matrix4x4f {
+        match self {
+            IntersectionObject::Sphere(sphere) => sphere.get_transform(),
+            IntersectionObject::Disc(disc) => disc.get_transform(),
+        }
+    }
+
+    pub fn normal_at(&self, world_point: &Point3f) -> Vector3f {
+        match self {
+            IntersectionObject::Sphere(sphere) => sphere.normal_at(world_point),
+            IntersectionObject::Disc(disc) => disc.normal_at(world_point),
+        }
+    }
 }
 
 pub struct Intersections<'a> {
     intersections: Vec<Intersection<'a>>,
 }
 
-fn sort_intersections<'a>(intersections: &mut Vec<Intersection<'a>>) {
-    intersections.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+pub fn sort_intersections(intersections: &mut [Intersection]) {
+    intersections.sort_by(Intersection::cmp_by_t);
 }
 
 // TODO: We don't know how this data structure will be used in the future. Right now,
@@ -146,7 +565,7 @@ impl<'a> Intersections<'a> {
         Self { intersections }
     }
 
-    pub fn iter(&self) -> std::slice::Iter<Intersection<'a>> {
+    pub fn iter(&self) -> std::slice::Iter<'_, Intersection<'a>> {
         self.intersections.iter()
     }
 
@@ -164,14 +583,110 @@ impl<'a> Intersections<'a> {
         }
     }
 
+    pub fn merge(mut self, mut other: Intersections<'a>) -> Self {
+        self.intersections.append(&mut other.intersections);
+        sort_intersections(&mut self.intersections);
+        self
+    }
+
     pub fn hit(&self) -> Option<&Intersection<'a>> {
         // assumption is that list is already sorted
         self.intersections.iter().find(|x| x.t >= 0.0)
     }
+
+    /// Same as `hit()`, but skips any intersection with `object` (e.g. the surface that
+    /// the ray originated from).
+    pub fn hit_excluding(&self, object: &IntersectionObject) -> Option<&Intersection<'a>> {
+        self.intersections
+            .iter()
+            .find(|x| x.t >= 0.0 && !x.object.is_same_object(object))
+    }
+
+    /// Removes intersections that are less than `eps` after an intersection with the same
+    /// object, e.g. duplicate hits at a grazing angle. The earliest one is kept, and hits
+    /// on different objects are never merged. Note that this also collapses the two
+    /// hits of a ray that exactly touches a surface.
+    pub fn dedup_coincident(&mut self, eps: f64) {
+        let mut kept: Vec<Intersection<'a>> = Vec::with_capacity(self.intersections.len());
+
+        std::mem::take(&mut self.intersections)
+            .into_iter()
+            .for_each(|x| {
+                let is_duplicate = kept
+                    .iter()
+                    .rev()
+                    .take_while(|k| x.t - k.t < eps)
+                    .any(|k| k.object.is_same_object(&x.object));
+                if !is_duplicate {
+                    kept.push(x);
+                }
+            });
+
+        self.intersections = kept;
+    }
+
+    /// Refractive indices (n1, n2) of the materials the ray is leaving and entering at
+    /// the intersection with the given index, found by tracking which objects the ray
+    /// is inside of (see page 151). Outside of every object counts as a vacuum (1.0).
+    pub fn containers_at(&self, hit_index: usize) -> (f64, f64) {
+        assert!(
+            hit_index < self.len(),
+            "Hit index {} out of range for {} intersections",
+            hit_index,
+            self.len()
+        );
+
+        let refractive_index = |containers: &[IntersectionObject]| {
+            containers.last().map_or(Material::VACUUM_IOR, |object| {
+                object.material().refractive_index
+            })
+        };
+        let mut containers: Vec<IntersectionObject<'a>> = vec![];
+
+        for (i, x) in self.intersections.iter().enumerate() {
+            let n1 = refractive_index(&containers);
+
+            match containers
+                .iter()
+                .position(|object| object.is_same_object(&x.object))
+            {
+                Some(index) => {
+                    containers.remove(index);
+                }
+                None => containers.push(x.object),
+            }
+
+            if i == hit_index {
+                return (n1, refractive_index(&containers));
+            }
+        }
+
+        unreachable!("hit_index is checked to be in range above")
+    }
+}
+
+impl<'a> IntoIterator for Intersections<'a> {
+    type Item = Intersection<'a>;
+    type IntoIter = std::vec::IntoIter<Intersection<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.intersections.into_iter()
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b Intersections<'a> {
+    type Item = &'b Intersection<'a>;
+    type IntoIter = std::slice::Iter<'b, Intersection<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::math::assert_float_eq;
+
     use super::*;
 
     #[test]
@@ -213,10 +728,273 @@ mod tests {
             assert_eq!(result.iter().map(|x| x.t).collect::<Vec<_>>(), expected);
             assert!(result.iter().all(|x| match x.get_object() {
                 IntersectionObject::Sphere(sphere) => std::ptr::eq(*sphere, &s),
+                _ => false,
             }));
         });
     }
 
+    #[test]
+    fn test_intersect_into() {
+        let s1 = Sphere::default();
+        let s2 = Sphere::new(
+            Matrix4x4f::translation(Vector3f::new(0.0, 0.0, 3.0)),
+            Material::default(),
+        );
+        let d = Disc::new(
+            Matrix4x4f::rotation_x(std::f64::consts::PI / 2.0),
+            Material::default(),
+            1.0,
+            0.0,
+        );
+        let r = Ray::new(Point3f::new(0.0, 0.0, -5.0), Vector3f::new(0.0, 0.0, 1.0));
+
+        let mut buf = vec![];
+        r.intersect_sphere_into(&s2, &mut buf);
+        r.intersect_sphere_into(&s1, &mut buf);
+        r.intersect_disc_into(&d, &mut buf);
+        sort_intersections(&mut buf);
+
+        let expected = r
+            .intersect_sphere(&s1)
+            .merge(r.intersect_sphere(&s2))
+            .merge(r.intersect_disc(&d));
+        assert_eq!(buf, expected.iter().copied().collect::<Vec<_>>());
+        assert_eq!(
+            buf.iter().map(|x| x.t()).collect::<Vec<_>>(),
+            vec![4.0, 5.0, 6.0, 7.0, 9.0]
+        );
+
+        // reusing the buffer for a ray that only hits s1
+        buf.clear();
+        let r = Ray::new(Point3f::new(0.0, 0.0, 0.0), Vector3f::new(0.0, 1.0, 0.0));
+        [&s1, &s2]
+            .into_iter()
+            .for_each(|s| r.intersect_sphere_into(s, &mut buf));
+        sort_intersections(&mut buf);
+        assert_eq!(
+            buf,
+            r.intersect_sphere(&s1).iter().copied().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            buf.iter().map(|x| x.t()).collect::<Vec<_>>(),
+            vec![-1.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn test_shape_kind() {
+        let sphere = Sphere::default();
+        let disc = Disc::default();
+        let shapes: Vec<ShapeKind> = vec![sphere.into(), disc.into()];
+        let r = Ray::new(Point3f::new(0.0, 0.5, -5.0), Vector3f::new(0.0, 0.0, 1.0));
+
+        let collect = |xs: Intersections| xs.iter().map(|x| x.t()).collect::<Vec<_>>();
+        assert_eq!(
+            collect(r.intersect(&shapes[0])),
+            collect(r.intersect_sphere(&sphere))
+        );
+        assert_eq!(
+            collect(r.intersect(&shapes[1])),
+            collect(r.intersect_disc(&disc))
+        );
+        assert!(r
+            .intersect(&shapes[0])
+            .iter()
+            .all(|x| x.get_object().id() == sphere.id()));
+
+        let r = Ray::new(Point3f::new(0.0, 5.0, 0.0), Vector3f::new(0.0, -1.0, 0.0));
+        let mut buf = vec![];
+        shapes
+            .iter()
+            .for_each(|shape| r.intersect_into(shape, &mut buf));
+        sort_intersections(&mut buf);
+        assert_eq!(
+            buf.iter().map(|x| x.t()).collect::<Vec<_>>(),
+            vec![4.0, 5.0, 6.0]
+        );
+        assert!(buf[1].get_object().is_same_object(&shapes[1].as_object()));
+
+        assert_eq!(shapes[0].id(), sphere.id());
+        assert_eq!(shapes[1].material(), disc.get_material());
+        assert_eq!(shapes[0].transform(), sphere.get_transform());
+        let p = Point3f::new(0.0, 1.0, 0.0);
+        assert_float_eq(shapes[0].normal_at(&p), sphere.normal_at(&p));
+    }
+
+    #[test]
+    fn test_shape_kind_includes() {
+        let sphere: ShapeKind = Sphere::default().into();
+        let other: ShapeKind = Sphere::default().into();
+        let disc: ShapeKind = Disc::default().into();
+
+        assert!(sphere.includes(sphere.id()));
+        assert!(!sphere.includes(other.id()));
+        assert!(!sphere.includes(disc.id()));
+        assert!(disc.includes(disc.id()));
+    }
+
+    #[test]
+    fn test_disc_intersect() {
+        let d = Disc::new(Matrix4x4f::identity(), Material::default(), 2.0, 0.0);
+        let down = Vector3f::new(0.0, -1.0, 0.0);
+
+        let xs = Ray::new(Point3f::new(1.0, 3.0, 1.0), down).intersect_disc(&d);
+        assert_eq!(xs.iter().map(|x| x.t()).collect::<Vec<_>>(), vec![3.0]);
+        assert!(xs
+            .hit()
+            .unwrap()
+            .get_object()
+            .is_same_object(&IntersectionObject::Disc(&d)));
+
+        let xs = Ray::new(Point3f::new(2.0, 3.0, 1.0), down).intersect_disc(&d);
+        assert!(xs.is_empty());
+
+        let xs =
+            Ray::new(Point3f::new(0.0, 1.0, 0.0), Vector3f::new(1.0, 0.0, 0.0)).intersect_disc(&d);
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn test_disc_annulus_intersect() {
+        let d = Disc::new(Matrix4x4f::identity(), Material::default(), 2.0, 1.0);
+        let down = Vector3f::new(0.0, -1.0, 0.0);
+
+        assert!(Ray::new(Point3f::new(0.5, 3.0, 0.0), down)
+            .intersect_disc(&d)
+            .is_empty());
+        assert_eq!(
+            Ray::new(Point3f::new(0.0, 3.0, 1.5), down)
+                .intersect_disc(&d)
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_disc_normal_at() {
+        let d = Disc::default();
+        assert_eq!(
+            d.normal_at(&Point3f::new(0.5, 0.0, 0.5)),
+            Vector3f::new(0.0, 1.0, 0.0)
+        );
+
+        let d = Disc::new(
+            Matrix4x4f::rotation_x(std::f64::consts::PI / 2.0),
+            Material::default(),
+            1.0,
+            0.0,
+        );
+        assert_float_eq(
+            d.normal_at(&Point3f::new(0.0, 0.0, 0.0)),
+            Vector3f::new(0.0, 0.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn test_bounding_box_longest_axis_and_area() {
+        let b = BoundingBox::new(Point3f::new(-1.0, -1.0, -1.0), Point3f::new(1.0, 1.0, 1.0));
+        assert_eq!(b.longest_axis(), Axis::X);
+        assert_eq!(b.area(), 24.0);
+
+        let b = BoundingBox::new(Point3f::new(0.0, 0.0, 0.0), Point3f::new(1.0, 3.0, 2.0));
+        assert_eq!(b.longest_axis(), Axis::Y);
+        assert_eq!(b.area(), 22.0);
+
+        let b = BoundingBox::new(Point3f::new(0.0, 0.0, 0.0), Point3f::new(1.0, 1.0, 2.0));
+        assert_eq!(b.longest_axis(), Axis::Z);
+    }
+
+    #[test]
+    fn test_ray_intersect_box() {
+        let b = BoundingBox::new(Point3f::new(-1.0, -1.0, -1.0), Point3f::new(1.0, 1.0, 1.0));
+
+        [
+            ((5.0, 0.5, 0.0), (-1.0, 0.0, 0.0), Some((4.0, 6.0))),
+            ((-5.0, 0.5, 0.0), (1.0, 0.0, 0.0), Some((4.0, 6.0))),
+            ((0.5, 5.0, 0.0), (0.0, -1.0, 0.0), Some((4.0, 6.0))),
+            ((0.5, 0.0, -5.0), (0.0, 0.0, 1.0), Some((4.0, 6.0))),
+            ((0.0, 0.5, 0.0), (0.0, 0.0, 1.0), Some((-1.0, 1.0))),
+            ((-2.0, 0.0, 0.0), (0.2673, 0.5345, 0.8018), None),
+            ((0.0, -2.0, 0.0), (0.8018, 0.2673, 0.5345), None),
+            ((2.0, 0.0, 2.0), (0.0, 0.0, -1.0), None),
+            ((0.0, 2.0, 2.0), (0.0, -1.0, 0.0), None),
+        ]
+        .into_iter()
+        .for_each(|(origin, direction, expected)| {
+            let r = Ray::new(
+                Point3f::new(origin.0, origin.1, origin.2),
+                Vector3f::new(direction.0, direction.1, direction.2),
+            );
+            assert_eq!(r.intersect_box(&b), expected);
+        });
+    }
+
+    #[test]
+    fn test_ray_intersects_box() {
+        let b = BoundingBox::new(Point3f::new(-1.0, -1.0, -1.0), Point3f::new(1.0, 1.0, 1.0));
+        let towards = Ray::new(Point3f::new(0.0, 0.0, -5.0), Vector3f::new(0.0, 0.0, 1.0));
+        let inside = Ray::new(Point3f::new(0.0, 0.0, 0.0), Vector3f::new(0.0, 0.0, 1.0));
+        let away = Ray::new(Point3f::new(0.0, 0.0, 5.0), Vector3f::new(0.0, 0.0, 1.0));
+        let miss = Ray::new(Point3f::new(3.0, 0.0, -5.0), Vector3f::new(0.0, 0.0, 1.0));
+
+        assert!(towards.intersects_box(&b));
+        assert!(inside.intersects_box(&b));
+        assert!(inside.intersect_box(&b).unwrap().0 < 0.0);
+        assert!(!away.intersects_box(&b));
+        assert!(!miss.intersects_box(&b));
+    }
+
+    #[test]
+    fn test_bounding_box_split_bounds() {
+        [
+            // perfect cube
+            (
+                (-1.0, -4.0, -5.0),
+                (9.0, 6.0, 5.0),
+                (4.0, 6.0, 5.0),
+                (4.0, -4.0, -5.0),
+            ),
+            // wide along x
+            (
+                (-1.0, -2.0, -3.0),
+                (9.0, 5.5, 3.0),
+                (4.0, 5.5, 3.0),
+                (4.0, -2.0, -3.0),
+            ),
+            // wide along y
+            (
+                (-1.0, -2.0, -3.0),
+                (5.0, 8.0, 3.0),
+                (5.0, 3.0, 3.0),
+                (-1.0, 3.0, -3.0),
+            ),
+            // wide along z
+            (
+                (-1.0, -2.0, -3.0),
+                (5.0, 3.0, 7.0),
+                (5.0, 3.0, 2.0),
+                (-1.0, -2.0, 2.0),
+            ),
+        ]
+        .into_iter()
+        .for_each(|(min, max, left_max, right_min)| {
+            let min = Point3f::new(min.0, min.1, min.2);
+            let max = Point3f::new(max.0, max.1, max.2);
+            let (left, right) = BoundingBox::new(min, max).split_bounds();
+
+            assert_eq!(left.get_min(), min);
+            assert_eq!(
+                left.get_max(),
+                Point3f::new(left_max.0, left_max.1, left_max.2)
+            );
+            assert_eq!(
+                right.get_min(),
+                Point3f::new(right_min.0, right_min.1, right_min.2)
+            );
+            assert_eq!(right.get_max(), max);
+        });
+    }
+
     #[test]
     fn test_intersection_new() {
         let s = Sphere::default();
@@ -225,9 +1003,30 @@ mod tests {
         assert_eq!(i.t(), 3.5);
         match i.get_object() {
             IntersectionObject::Sphere(sphere) => assert!(std::ptr::eq(*sphere, &s)),
+            _ => panic!("Not a sphere"),
         }
     }
 
+    #[test]
+    fn test_intersection_cmp_by_t() {
+        use std::cmp::Ordering;
+
+        let s = Sphere::default();
+        let i = |t: f64| Intersection::new(t, IntersectionObject::Sphere(&s));
+
+        assert_eq!(i(1.0).cmp_by_t(&i(2.0)), Ordering::Less);
+        assert_eq!(i(2.0).cmp_by_t(&i(2.0)), Ordering::Equal);
+        assert_eq!(i(f64::NAN).cmp_by_t(&i(f64::INFINITY)), Ordering::Greater);
+
+        let mut xs = [i(5.0), i(-3.0), i(f64::NAN), i(7.0), i(0.0), i(2.0)];
+        xs.sort_by(Intersection::cmp_by_t);
+        assert_eq!(
+            xs.iter().take(5).map(|x| x.t()).collect::<Vec<_>>(),
+            vec![-3.0, 0.0, 2.0, 5.0, 7.0]
+        );
+        assert!(xs[5].t().is_nan());
+    }
+
     #[test]
     fn test_intersections_new() {
         let s = Sphere::default();
@@ -239,6 +1038,7 @@ mod tests {
         assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![1.0, 2.0]);
         assert!(xs.iter().all(|x| match x.get_object() {
             IntersectionObject::Sphere(sphere) => std::ptr::eq(*sphere, &s),
+            _ => false,
         }));
     }
 
@@ -276,6 +1076,221 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_intersections_hit_excluding() {
+        let r = Ray::new(Point3f::new(0.0, 0.0, -5.0), Vector3f::new(0.0, 0.0, 1.0));
+        let front = Sphere::default();
+        let back = Sphere::new(
+            Matrix4x4f::translation(Vector3f::new(0.0, 0.0, 1.0)),
+            Material::default(),
+        );
+
+        let xs = Intersections::new(
+            r.intersect_sphere(&front)
+                .iter()
+                .chain(r.intersect_sphere(&back).iter())
+                .copied()
+                .collect(),
+        );
+
+        assert_eq!(xs.hit().map(|x| x.t()), Some(4.0));
+        assert!(xs
+            .hit()
+            .unwrap()
+            .get_object()
+            .is_same_object(&IntersectionObject::Sphere(&front)));
+
+        let hit = xs
+            .hit_excluding(&IntersectionObject::Sphere(&front))
+            .unwrap();
+        assert_eq!(hit.t(), 5.0);
+        assert!(hit
+            .get_object()
+            .is_same_object(&IntersectionObject::Sphere(&back)));
+
+        let xs = r.intersect_sphere(&front);
+        assert_eq!(xs.hit_excluding(&IntersectionObject::Sphere(&front)), None);
+        assert_eq!(
+            xs.hit_excluding(&IntersectionObject::Sphere(&back)),
+            xs.hit()
+        );
+    }
+
+    #[test]
+    fn test_intersections_containers_at() {
+        let glass = |transform: Matrix4x4f, refractive_index: f64| {
+            Sphere::new(
+                transform,
+                Material {
+                    refractive_index,
+                    ..Material::glass()
+                },
+            )
+        };
+        let a = glass(Matrix4x4f::scaling(Vector3f::new(2.0, 2.0, 2.0)), 1.5);
+        let b = glass(Matrix4x4f::translation(Vector3f::new(0.0, 0.0, -0.25)), 2.0);
+        let c = glass(Matrix4x4f::translation(Vector3f::new(0.0, 0.0, 0.25)), 2.5);
+        let expected = [
+            (1.0, 1.5),
+            (1.5, 2.0),
+            (2.0, 2.5),
+            (2.5, 2.5),
+            (2.5, 1.5),
+            (1.5, 1.0),
+        ];
+
+        let xs = Intersections::new(vec![
+            Intersection::new(2.0, IntersectionObject::Sphere(&a)),
+            Intersection::new(2.75, IntersectionObject::Sphere(&b)),
+            Intersection::new(3.25, IntersectionObject::Sphere(&c)),
+            Intersection::new(4.75, IntersectionObject::Sphere(&b)),
+            Intersection::new(5.25, IntersectionObject::Sphere(&c)),
+            Intersection::new(6.0, IntersectionObject::Sphere(&a)),
+        ]);
+        expected.iter().enumerate().for_each(|(i, expected)| {
+            assert_eq!(xs.containers_at(i), *expected);
+        });
+
+        // same result when the intersections come from actual rays
+        let r = Ray::new(Point3f::new(0.0, 0.0, -4.0), Vector3f::new(0.0, 0.0, 1.0));
+        let xs = r
+            .intersect_sphere(&a)
+            .merge(r.intersect_sphere(&b))
+            .merge(r.intersect_sphere(&c));
+        expected.iter().enumerate().for_each(|(i, expected)| {
+            assert_eq!(xs.containers_at(i), *expected);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_intersections_containers_at_out_of_range() {
+        Intersections::new_empty().containers_at(0);
+    }
+
+    #[test]
+    fn test_intersections_dedup_coincident() {
+        let s1 = Sphere::default();
+        let s2 = Sphere::default();
+        let ts = |xs: &Intersections| xs.iter().map(|x| x.t()).collect::<Vec<_>>();
+
+        let mut xs = Intersections::new(vec![
+            Intersection::new(4.0 + 1e-9, IntersectionObject::Sphere(&s1)),
+            Intersection::new(4.0, IntersectionObject::Sphere(&s1)),
+        ]);
+        xs.dedup_coincident(1e-6);
+        assert_eq!(ts(&xs), vec![4.0]);
+
+        let mut xs = Intersections::new(vec![
+            Intersection::new(4.0, IntersectionObject::Sphere(&s1)),
+            Intersection::new(4.1, IntersectionObject::Sphere(&s1)),
+        ]);
+        xs.dedup_coincident(1e-6);
+        assert_eq!(ts(&xs), vec![4.0, 4.1]);
+
+        // other objects are kept, even in between duplicates
+        let mut xs = Intersections::new(vec![
+            Intersection::new(1.0, IntersectionObject::Sphere(&s1)),
+            Intersection::new(1.0 + 1e-9, IntersectionObject::Sphere(&s2)),
+            Intersection::new(1.0 + 2e-9, IntersectionObject::Sphere(&s1)),
+            Intersection::new(3.0, IntersectionObject::Sphere(&s2)),
+        ]);
+        xs.dedup_coincident(1e-6);
+        assert_eq!(ts(&xs), vec![1.0, 1.0 + 1e-9, 3.0]);
+        assert_eq!(xs.iter().next().unwrap().get_object().id(), s1.id());
+
+        let mut xs = Intersections::new_empty();
+        xs.dedup_coincident(1e-6);
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn test_intersections_merge() {
+        let s1 = Sphere::default();
+        let s2 = Sphere::default();
+        let xs1 = Intersections::new(vec![
+            Intersection::new(1.0, IntersectionObject::Sphere(&s1)),
+            Intersection::new(4.0, IntersectionObject::Sphere(&s1)),
+        ]);
+        let xs2 = Intersections::new(vec![
+            Intersection::new(-2.0, IntersectionObject::Sphere(&s2)),
+            Intersection::new(2.5, IntersectionObject::Sphere(&s2)),
+        ]);
+
+        let xs = xs1.merge(xs2);
+        assert_eq!(
+            xs.iter().map(|x| x.t()).collect::<Vec<_>>(),
+            vec![-2.0, 1.0, 2.5, 4.0]
+        );
+        assert_eq!(
+            xs.iter().map(|x| x.get_object().id()).collect::<Vec<_>>(),
+            vec![s2.id(), s1.id(), s2.id(), s1.id()]
+        );
+
+        let xs = xs.merge(Intersections::new_empty());
+        assert_eq!(xs.len(), 4);
+    }
+
+    #[test]
+    fn test_intersections_into_iter() {
+        let s = Sphere::default();
+        let xs = Intersections::new(vec![
+            Intersection::new(3.0, IntersectionObject::Sphere(&s)),
+            Intersection::new(-1.0, IntersectionObject::Sphere(&s)),
+            Intersection::new(2.0, IntersectionObject::Sphere(&s)),
+        ]);
+
+        let mut ts = vec![];
+        for x in &xs {
+            ts.push(x.t());
+        }
+        assert_eq!(ts, vec![-1.0, 2.0, 3.0]);
+
+        let mut ts = vec![];
+        for x in xs {
+            ts.push(x.t());
+        }
+        assert_eq!(ts, vec![-1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_intersections_nan() {
+        let s = Sphere::default();
+        let i1 = Intersection::new(f64::NAN, IntersectionObject::Sphere(&s));
+        let i2 = Intersection::new(3.0, IntersectionObject::Sphere(&s));
+        let i3 = Intersection::new(-1.0, IntersectionObject::Sphere(&s));
+        let i4 = Intersection::new(-f64::NAN, IntersectionObject::Sphere(&s));
+        let i5 = Intersection::new(2.0, IntersectionObject::Sphere(&s));
+        let xs = Intersections::new(vec![i1, i2, i3, i4, i5]);
+
+        assert_eq!(xs.len(), 5);
+        assert_eq!(
+            xs.iter().take(3).map(|x| x.t).collect::<Vec<_>>(),
+            vec![-1.0, 2.0, 3.0]
+        );
+        assert!(xs.iter().skip(3).all(|x| x.t.is_nan()));
+        assert_eq!(xs.hit(), Some(&i5));
+    }
+
+    #[test]
+    fn test_intersection_object_dispatch() {
+        let s = Sphere::new(
+            Matrix4x4f::translation(Vector3f::new(0.0, 1.0, 0.0)),
+            Material::glass(),
+        );
+        let object = IntersectionObject::Sphere(&s);
+        let p = Point3f::new(0.0, 2.0, 0.0);
+
+        assert_eq!(object.material(), s.get_material());
+        assert_eq!(object.transform(), s.get_transform());
+        assert_float_eq(object.normal_at(&p), s.normal_at(&p));
+
+        let d = Disc::default();
+        let object = IntersectionObject::Disc(&d);
+        assert_eq!(object.material(), d.get_material());
+        assert_float_eq(object.normal_at(&p), Vector3f::new(0.0, 1.0, 0.0));
+    }
+
     #[test]
     fn test_ray_transform() {
         let r = Ray::new(Point3f::new(1.0, 2.0, 3.0), Vector3f::new(0.0, 1.0, 0.0));
@@ -287,14 +1302,112 @@ mod tests {
     }
 
     #[test]
-    fn test_sphere_default() {
+    fn test_ray_max_t() {
+        let r = Ray::new(Point3f::new(0.0, 0.0, -5.0), Vector3f::new(0.0, 0.0, 1.0));
+        assert_eq!(r.get_max_t(), f64::INFINITY);
+
+        let s = Sphere::new(
+            Matrix4x4f::translation(Vector3f::new(0.0, 0.0, 5.0)),
+            Material::default(),
+        );
+        assert_eq!(r.intersect_sphere(&s).hit().unwrap().t(), 9.0);
+
+        let short =
+            Ray::new(Point3f::new(0.0, 0.0, -5.0), Vector3f::new(0.0, 0.0, 1.0)).with_max_t(8.0);
+        assert!(short.intersect_sphere(&s).is_empty());
+        assert!(short.intersect_sphere(&s).hit().is_none());
+
+        // only the far side is cut off
+        let partial =
+            Ray::new(Point3f::new(0.0, 0.0, -5.0), Vector3f::new(0.0, 0.0, 1.0)).with_max_t(10.0);
         assert_eq!(
-            Sphere::default(),
-            Sphere {
-                transform: Matrix4x4f::identity(),
-                material: Material::default()
-            }
+            partial
+                .intersect_sphere(&s)
+                .iter()
+                .map(|x| x.t())
+                .collect::<Vec<_>>(),
+            vec![9.0]
         );
+
+        let d = Disc::default();
+        let r = Ray::new(Point3f::new(0.0, 5.0, 0.0), Vector3f::new(0.0, -1.0, 0.0));
+        assert_eq!(r.intersect_disc(&d).len(), 1);
+        assert!(r.with_max_t(4.0).intersect_disc(&d).is_empty());
+
+        let b = BoundingBox::new(Point3f::new(-1.0, -1.0, 4.0), Point3f::new(1.0, 1.0, 6.0));
+        let r = Ray::new(Point3f::new(0.0, 0.0, -5.0), Vector3f::new(0.0, 0.0, 1.0));
+        assert!(r.intersects_box(&b));
+        assert!(!r.with_max_t(8.0).intersects_box(&b));
+
+        // kept in object space
+        let m = Matrix4x4f::scaling(Vector3f::new(2.0, 2.0, 2.0));
+        assert_eq!(short.transform(&m).get_max_t(), 8.0);
+    }
+
+    #[test]
+    fn test_ray_reflect_at() {
+        let r = Ray::new(Point3f::new(0.0, 0.0, 0.0), Vector3f::new(1.0, 0.0, 0.0));
+        let reflected = r.reflect_at(
+            Point3f::new(5.0, 0.0, 0.0),
+            &Vector3f::new(-2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0, 0.0),
+        );
+
+        assert_float_eq(reflected.get_origin(), Point3f::new(5.0, 0.0, 0.0));
+        assert_float_eq(reflected.get_direction(), Vector3f::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_sphere_default() {
+        let s = Sphere::default();
+        assert_eq!(s.transform, Matrix4x4f::identity());
+        assert_eq!(s.material, Material::default());
+    }
+
+    #[test]
+    fn test_sphere_eq() {
+        let a = Sphere::new(
+            Matrix4x4f::identity()
+                .scale(Vector3f::new(0.1, 0.1, 0.1))
+                .translate(Vector3f::new(0.3, 0.0, 0.0)),
+            Material::default(),
+        );
+        let b = Sphere::new(
+            Matrix4x4f::scaling(Vector3f::new(0.1, 0.1, 0.1))
+                .translate(Vector3f::new(0.1, 0.0, 0.0))
+                .translate(Vector3f::new(0.2, 0.0, 0.0)),
+            Material::default(),
+        );
+        assert_ne!(a.transform, b.transform);
+        assert_eq!(a, b);
+
+        let c = Sphere::new(
+            Matrix4x4f::scaling(Vector3f::new(0.1, 0.1, 0.1)),
+            Material::default(),
+        );
+        assert_ne!(a, c);
+        assert_ne!(a, Sphere::glass());
+    }
+
+    #[test]
+    fn test_sphere_glass() {
+        let s = Sphere::glass();
+        assert_eq!(s.transform, Matrix4x4f::identity());
+        assert_eq!(s.get_material().transparency, 1.0);
+        assert_eq!(s.get_material().refractive_index, 1.5);
+    }
+
+    #[test]
+    fn test_sphere_id() {
+        let s1 = Sphere::default();
+        let s2 = Sphere::default();
+        assert_ne!(s1.id(), s2.id());
+        // equal in value, but not the same object
+        assert_eq!(s1, s2);
+
+        let s3 = s1;
+        assert_eq!(s1.id(), s3.id());
+        assert!(IntersectionObject::Sphere(&s1).is_same_object(&IntersectionObject::Sphere(&s3)));
+        assert!(!IntersectionObject::Sphere(&s1).is_same_object(&IntersectionObject::Sphere(&s2)));
     }
 
     #[test]
@@ -306,6 +1419,29 @@ mod tests {
         assert_eq!(s.transform, t);
     }
 
+    #[test]
+    fn test_sphere_identity_transform_intersect() {
+        let r = Ray::new(Point3f::new(0.3, 0.2, -5.0), Vector3f::new(0.0, 0.1, 1.0));
+        let identity = Sphere::default();
+        // not exactly identity, so it takes the general path
+        let general = Sphere::new(
+            Matrix4x4f::translation(Vector3f::new(0.0, 0.0, 1e-12)),
+            Material::default(),
+        );
+        assert!(identity.transform.is_identity());
+        assert!(!general.transform.is_identity());
+
+        let a = r.intersect_sphere(&identity);
+        let b = r.intersect_sphere(&general);
+        assert_eq!(a.len(), 2);
+        a.iter().zip(b.iter()).for_each(|(a, b)| {
+            assert!((a.t() - b.t()).abs() < 1e-9);
+        });
+
+        let p = r.position(a.hit().unwrap().t());
+        assert!(identity.normal_at(&p).dot(&general.normal_at(&p)) > 1.0 - 1e-9);
+    }
+
     #[test]
     fn test_sphere_transformed_intersect() {
         {
@@ -352,6 +1488,22 @@ mod tests {
         assert_eq!(n.normalize(), n);
     }
 
+    #[test]
+    fn test_sphere_normal_at_center() {
+        let s = Sphere::default();
+        assert_eq!(
+            s.normal_at(&Point3f::new(0.0, 0.0, 0.0)),
+            Vector3f::new(0.0, 1.0, 0.0)
+        );
+
+        let s =
+            Sphere::default().with_transform(Matrix4x4f::translation(Vector3f::new(1.0, 2.0, 3.0)));
+        assert_eq!(
+            s.normal_at(&Point3f::new(1.0, 2.0, 3.0)),
+            Vector3f::new(0.0, 1.0, 0.0)
+        );
+    }
+
     #[test]
     fn test_sphere_advanced_normal_at() {
         {
@@ -386,4 +1538,23 @@ mod tests {
         let s = Sphere::new(Matrix4x4f::identity(), m);
         assert_eq!(s.material, m);
     }
+
+    #[test]
+    fn test_sphere_set_material() {
+        let m = Material::glass();
+        let t = Matrix4x4f::scaling(Vector3f::new(2.0, 2.0, 2.0));
+
+        let mut s = Sphere::default();
+        s.set_material(m);
+        assert_eq!(s.get_material(), m);
+
+        assert_eq!(
+            Sphere::default().with_material(m).with_transform(t),
+            Sphere::new(t, m)
+        );
+        assert_eq!(
+            Disc::default().with_material(m).with_transform(t),
+            Disc::new(t, m, 1.0, 0.0)
+        );
+    }
 }
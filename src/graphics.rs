@@ -1,4 +1,7 @@
-use std::ops::{Add, Mul, Sub};
+use std::{
+    fmt,
+    ops::{Add, Mul, Sub},
+};
 
 use crate::math::FloatEq;
 
@@ -15,6 +18,46 @@ impl Color {
         Self { vals: [r, g, b] }
     }
 
+    /// `h` in degrees (wraps around 360), `s` and `v` from 0 to 1
+    pub fn from_hsv(h: f64, s: f64, v: f64) -> Self {
+        let c = v * s;
+        let h = h.rem_euclid(360.0) / 60.0;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let m = v - c;
+
+        let (r, g, b) = match h as usize {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+
+        Self::new(r + m, g + m, b + m)
+    }
+
+    /// Returns (h, s, v), see `from_hsv()`. Hue is 0 for grays.
+    pub fn to_hsv(&self) -> (f64, f64, f64) {
+        let (r, g, b) = (self.r(), self.g(), self.b());
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+
+        let h = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let s = if max == 0.0 { 0.0 } else { delta / max };
+
+        (h, s, max)
+    }
+
     pub fn r(&self) -> f64 {
         self.vals[0]
     }
@@ -27,6 +70,58 @@ impl Color {
         self.vals[2]
     }
 
+    /// Each component clamped to 0..1 and scaled to 0..255
+    pub fn to_rgb8(&self) -> [u8; 3] {
+        let to_u8 = |v: f64| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [to_u8(self.r()), to_u8(self.g()), to_u8(self.b())]
+    }
+
+    /// Relative luminance, using the Rec. 709 weights
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r() + 0.7152 * self.g() + 0.0722 * self.b()
+    }
+
+    pub fn grayscale(&self) -> Color {
+        let l = self.luminance();
+        Color::new(l, l, l)
+    }
+
+    /// Linear interpolation, t = 0 gives self, t = 1 gives other
+    pub fn lerp(&self, other: &Color, t: f64) -> Color {
+        *self + (*other - *self) * t
+    }
+
+    /// Same as `*`, but clamps each component to `0..1`. Use this for final
+    /// (display-referred) colors, e.g. tinting an already tonemapped pixel. Lighting
+    /// should keep using the linear `*`, so that bright (HDR) light is not lost before
+    /// it is summed up.
+    pub fn mul_saturating(&self, other: &Color) -> Color {
+        self.binary_op(other, |a, b| (a * b).clamp(0.0, 1.0))
+    }
+
+    /// Same as `* f64`, but clamps each component to `0..1`. See `mul_saturating()`.
+    pub fn mul_scalar_saturating(&self, s: f64) -> Color {
+        self.unary_op(|a| (a * s).clamp(0.0, 1.0))
+    }
+
+    /// Reinhard tone mapping (`c / (c + 1)` per component), which brings HDR colors
+    /// into `0..1` without clipping highlights. Negative components become 0.
+    pub fn tone_map_reinhard(&self) -> Color {
+        self.unary_op(|c| {
+            let c = c.max(0.0);
+            c / (c + 1.0)
+        })
+    }
+
+    /// Filmic tone mapping, using Narkowicz's fit of the ACES curve. Compared to
+    /// `tone_map_reinhard()` it keeps more contrast, but also darkens dim colors a bit.
+    pub fn tone_map_aces(&self) -> Color {
+        self.unary_op(|c| {
+            let c = c.max(0.0);
+            ((c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14)).clamp(0.0, 1.0)
+        })
+    }
+
     fn unary_op<F>(&self, op: F) -> Self
     where
         F: Fn(&f64) -> f64,
@@ -52,10 +147,26 @@ impl Color {
 
 impl FloatEq for Color {
     fn float_eq(&self, other: &Self) -> bool {
+        self.float_eq_within(other, f64::EPSILON)
+    }
+
+    fn float_eq_within(&self, other: &Self, eps: f64) -> bool {
         self.vals
             .iter()
             .zip(other.vals.iter())
-            .all(|(a, b)| a.float_eq(b))
+            .all(|(a, b)| a.float_eq_within(b, eps))
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rgb({}, {}, {})", self.r(), self.g(), self.b())
+    }
+}
+
+impl From<(f64, f64, f64)> for Color {
+    fn from((r, g, b): (f64, f64, f64)) -> Self {
+        Color::new(r, g, b)
     }
 }
 
@@ -92,29 +203,92 @@ impl Mul for Color {
     }
 }
 
+/// How to handle coordinates outside of the canvas when sampling
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum WrapMode {
+    // use the nearest edge pixel
+    Clamp,
+    // tile the canvas
+    Repeat,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CanvasError {
+    /// `w * h` pixels would not fit in memory.
+    TooLarge { w: usize, h: usize },
+}
+
+impl fmt::Display for CanvasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CanvasError::TooLarge { w, h } => write!(f, "Canvas size ({}, {}) is too large", w, h),
+        }
+    }
+}
+
+impl std::error::Error for CanvasError {}
+
+/// Rectangle of pixels within a canvas, with (`x`, `y`) as its top-left corner
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TileRegion {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct Canvas {
+    w: usize,
     px: Vec<Vec<Color>>,
 }
 
 impl Canvas {
     pub fn new(w: usize, h: usize) -> Self {
+        Self::try_new(w, h).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as `new()`, but fails instead of panicking (or aborting on allocation) when
+    /// `w * h` pixels (and a row header for each of the `h` rows) cannot be allocated.
+    pub fn try_new(w: usize, h: usize) -> Result<Self, CanvasError> {
+        let too_large = CanvasError::TooLarge { w, h };
+        let pixel_bytes = w
+            .checked_mul(h)
+            .and_then(|n| n.checked_mul(std::mem::size_of::<Color>()));
+        let row_bytes = h.checked_mul(std::mem::size_of::<Vec<Color>>());
+        let bytes = pixel_bytes
+            .zip(row_bytes)
+            .and_then(|(pixel_bytes, row_bytes)| pixel_bytes.checked_add(row_bytes));
+        if bytes.map_or(true, |bytes| bytes > isize::MAX as usize) {
+            return Err(too_large);
+        }
+
+        // reserve up front, so that running out of memory is an error instead of an abort
+        let mut px = Vec::new();
+        px.try_reserve_exact(h).map_err(|_| too_large)?;
+        for _ in 0..h {
+            let mut row = Vec::new();
+            row.try_reserve_exact(w).map_err(|_| too_large)?;
+            row.resize(w, Color::new(0.0, 0.0, 0.0));
+            px.push(row);
+        }
+
+        Ok(Self { w, px })
+    }
+
+    /// Canvas where each pixel is `f(x, y)`
+    pub fn from_fn<F>(w: usize, h: usize, f: F) -> Self
+    where
+        F: Fn(usize, usize) -> Color,
+    {
         Self {
-            px: std::iter::repeat(
-                std::iter::repeat(Color::new(0.0, 0.0, 0.0))
-                    .take(w)
-                    .collect(),
-            )
-            .take(h)
-            .collect(),
+            w,
+            px: (0..h).map(|y| (0..w).map(|x| f(x, y)).collect()).collect(),
         }
     }
 
     pub fn w(&self) -> usize {
-        if self.px.is_empty() {
-            0
-        } else {
-            self.px[0].len()
-        }
+        self.w
     }
 
     pub fn h(&self) -> usize {
@@ -149,11 +323,448 @@ impl Canvas {
         self.assert_bounds(x, y);
         self.px[y][x] = color;
     }
+
+    /// Bilinearly filtered color at (u, v), where (0, 0) is the top-left corner of the
+    /// canvas and (1, 1) is the bottom-right corner. An empty canvas is black everywhere.
+    pub fn sample_uv(&self, u: f64, v: f64, wrap: WrapMode) -> Color {
+        if self.w() == 0 || self.h() == 0 {
+            return Color::BLACK;
+        }
+
+        let (u, v) = match wrap {
+            WrapMode::Clamp => (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0)),
+            WrapMode::Repeat => (u.rem_euclid(1.0), v.rem_euclid(1.0)),
+        };
+        let (w, h) = (self.w() as isize, self.h() as isize);
+
+        // pixel centers are at (x + 0.5, y + 0.5)
+        let fx = u * self.w() as f64 - 0.5;
+        let fy = v * self.h() as f64 - 0.5;
+        let (x0, y0) = (fx.floor(), fy.floor());
+        let (tx, ty) = (fx - x0, fy - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+
+        let px = |x: isize, y: isize| {
+            let (x, y) = match wrap {
+                WrapMode::Clamp => (x.clamp(0, w - 1), y.clamp(0, h - 1)),
+                WrapMode::Repeat => (x.rem_euclid(w), y.rem_euclid(h)),
+            };
+            self.px[y as usize][x as usize]
+        };
+
+        let top = px(x0, y0).lerp(&px(x0 + 1, y0), tx);
+        let bottom = px(x0, y0 + 1).lerp(&px(x0 + 1, y0 + 1), tx);
+        top.lerp(&bottom, ty)
+    }
+
+    /// Row by row, 3 bytes per pixel
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        self.iter_pixels()
+            .flat_map(|(_, _, color)| color.to_rgb8())
+            .collect()
+    }
+
+    /// Row by row, 4 bytes per pixel, alpha is always 255
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.iter_pixels()
+            .flat_map(|(_, _, color)| {
+                let [r, g, b] = color.to_rgb8();
+                [r, g, b, 255]
+            })
+            .collect()
+    }
+
+    /// Every pixel as (x, y, color), row by row
+    pub fn iter_pixels(&self) -> impl Iterator<Item = (usize, usize, Color)> + '_ {
+        self.px
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, c)| (x, y, *c)))
+    }
+
+    pub fn map_pixels<F>(&self, f: F) -> Canvas
+    where
+        F: Fn(usize, usize, Color) -> Color,
+    {
+        Canvas {
+            w: self.w,
+            px: self
+                .px
+                .iter()
+                .enumerate()
+                .map(|(y, row)| row.iter().enumerate().map(|(x, c)| f(x, y, *c)).collect())
+                .collect(),
+        }
+    }
+
+    /// Same as `write_px()`, but silently ignores pixels outside of the canvas
+    fn write_px_clipped(&mut self, x: isize, y: isize, color: Color) {
+        if x >= 0 && y >= 0 && (x as usize) < self.w() && (y as usize) < self.h() {
+            self.px[y as usize][x as usize] = color;
+        }
+    }
+
+    /// Draws a line between both points (inclusive) with Bresenham's algorithm. The line
+    /// is clipped to the canvas first, so far away endpoints are cheap.
+    pub fn draw_line(&mut self, from: (isize, isize), to: (isize, isize), color: Color) {
+        let Some((from, to)) = self.clip_line(from, to) else {
+            return;
+        };
+
+        let (mut x, mut y) = from;
+        let dx = (to.0 - x).abs();
+        let dy = -(to.1 - y).abs();
+        let step_x = if x < to.0 { 1 } else { -1 };
+        let step_y = if y < to.1 { 1 } else { -1 };
+        let mut err = dx + dy;
+
+        loop {
+            self.write_px_clipped(x, y, color);
+            if (x, y) == to {
+                break;
+            }
+
+            let err2 = 2 * err;
+            if err2 >= dy {
+                err += dy;
+                x += step_x;
+            }
+            if err2 <= dx {
+                err += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Connects each point to the next one with `draw_line()`
+    pub fn draw_polyline(&mut self, points: &[(isize, isize)], color: Color) {
+        points
+            .windows(2)
+            .for_each(|line| self.draw_line(line[0], line[1], color));
+    }
+
+    /// Same as `draw_polyline()`, but also connects the last point back to the first
+    pub fn draw_polygon(&mut self, points: &[(isize, isize)], color: Color) {
+        self.draw_polyline(points, color);
+        if let (Some(first), Some(last)) = (points.first(), points.last()) {
+            self.draw_line(*last, *first, color);
+        }
+    }
+
+    /// Part of the line inside of the canvas (Liang-Barsky), or `None` if it misses
+    fn clip_line(
+        &self,
+        from: (isize, isize),
+        to: (isize, isize),
+    ) -> Option<((isize, isize), (isize, isize))> {
+        if self.w() == 0 || self.h() == 0 {
+            return None;
+        }
+
+        let (x0, y0) = (from.0 as f64, from.1 as f64);
+        let (dx, dy) = (to.0 as f64 - x0, to.1 as f64 - y0);
+        let (max_x, max_y) = ((self.w() - 1) as f64, (self.h() - 1) as f64);
+        let mut t0: f64 = 0.0;
+        let mut t1: f64 = 1.0;
+
+        for (p, q) in [(-dx, x0), (dx, max_x - x0), (-dy, y0), (dy, max_y - y0)] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+            } else {
+                let r = q / p;
+                if p < 0.0 {
+                    t0 = t0.max(r);
+                } else {
+                    t1 = t1.min(r);
+                }
+                if t0 > t1 {
+                    return None;
+                }
+            }
+        }
+
+        let point = |t: f64| {
+            (
+                ((x0 + t * dx).round() as isize).clamp(0, self.w() as isize - 1),
+                ((y0 + t * dy).round() as isize).clamp(0, self.h() as isize - 1),
+            )
+        };
+        Some((point(t0), point(t1)))
+    }
+
+    /// Outline of a circle (midpoint circle algorithm), clipped to the canvas
+    pub fn draw_circle(&mut self, cx: isize, cy: isize, radius: usize, color: Color) {
+        let mut x = radius as isize;
+        let mut y = 0;
+        let mut err = 1 - x;
+
+        while x >= y {
+            [
+                (x, y),
+                (y, x),
+                (-y, x),
+                (-x, y),
+                (-x, -y),
+                (-y, -x),
+                (y, -x),
+                (x, -y),
+            ]
+            .into_iter()
+            .for_each(|(dx, dy)| self.write_px_clipped(cx + dx, cy + dy, color));
+
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Filled circle, clipped to the canvas
+    pub fn fill_circle(&mut self, cx: isize, cy: isize, radius: usize, color: Color) {
+        let r = radius as isize;
+
+        (-r..=r).for_each(|dy| {
+            (-r..=r)
+                .filter(|dx| dx * dx + dy * dy <= r * r)
+                .for_each(|dx| self.write_px_clipped(cx + dx, cy + dy, color));
+        });
+    }
+
+    /// Panics if the requested region does not fit inside the canvas.
+    pub fn crop(&self, x: usize, y: usize, w: usize, h: usize) -> Canvas {
+        assert!(
+            x + w <= self.w() && y + h <= self.h(),
+            "Crop region ({}, {}) of size ({}, {}) out of range for size ({}, {})",
+            x,
+            y,
+            w,
+            h,
+            self.w(),
+            self.h()
+        );
+
+        Canvas {
+            w,
+            px: self.px[y..y + h]
+                .iter()
+                .map(|row| row[x..x + w].to_vec())
+                .collect(),
+        }
+    }
+
+    /// Nearest-neighbor resize, each new pixel takes the source pixel its top-left corner
+    /// falls on. Resizing an empty canvas gives a black one.
+    pub fn resize_nearest(&self, new_w: usize, new_h: usize) -> Canvas {
+        if self.w() == 0 || self.h() == 0 {
+            return Canvas::new(new_w, new_h);
+        }
+
+        Canvas {
+            w: new_w,
+            px: (0..new_h)
+                .map(|y| {
+                    let src_y = y * self.h() / new_h;
+                    (0..new_w)
+                        .map(|x| self.px[src_y][x * self.w() / new_w])
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    pub fn flip_vertical(&self) -> Canvas {
+        Canvas {
+            w: self.w,
+            px: self.px.iter().rev().cloned().collect(),
+        }
+    }
+
+    pub fn flip_horizontal(&self) -> Canvas {
+        Canvas {
+            w: self.w,
+            px: self
+                .px
+                .iter()
+                .map(|row| row.iter().rev().copied().collect())
+                .collect(),
+        }
+    }
+
+    /// Copies `src` onto this canvas, with the top-left corner of `src` placed at
+    /// (`dest_x`, `dest_y`). Pixels that fall outside of this canvas are dropped.
+    pub fn blit(&mut self, src: &Canvas, dest_x: usize, dest_y: usize) {
+        (0..src.h())
+            .take(self.h().saturating_sub(dest_y))
+            .for_each(|y| {
+                (0..src.w())
+                    .take(self.w().saturating_sub(dest_x))
+                    .for_each(|x| {
+                        self.px[dest_y + y][dest_x + x] = src.px[y][x];
+                    });
+            });
+    }
+
+    /// Splits the canvas into tiles of `tile_w` x `tile_h`, row by row. Tiles along the
+    /// right and bottom edges are smaller if the canvas size is not a multiple of the
+    /// tile size.
+    pub fn tiles(&self, tile_w: usize, tile_h: usize) -> impl Iterator<Item = TileRegion> {
+        assert!(
+            tile_w > 0 && tile_h > 0,
+            "Tile size ({}, {}) must not be empty",
+            tile_w,
+            tile_h
+        );
+
+        let (w, h) = (self.w(), self.h());
+        (0..h).step_by(tile_h).flat_map(move |y| {
+            (0..w).step_by(tile_w).map(move |x| TileRegion {
+                x,
+                y,
+                w: tile_w.min(w - x),
+                h: tile_h.min(h - y),
+            })
+        })
+    }
+
+    /// Same as `blit()`, but blends `src` over this canvas instead of replacing pixels:
+    /// `dst * (1 - alpha) + src * alpha`
+    pub fn overlay(&mut self, src: &Canvas, dest_x: usize, dest_y: usize, alpha: f64) {
+        (0..src.h())
+            .take(self.h().saturating_sub(dest_y))
+            .for_each(|y| {
+                (0..src.w())
+                    .take(self.w().saturating_sub(dest_x))
+                    .for_each(|x| {
+                        let dst = &mut self.px[dest_y + y][dest_x + x];
+                        *dst = dst.lerp(&src.px[y][x], alpha);
+                    });
+            });
+    }
+
+    /// Number of pixels that are not `float_eq` to the pixel at the same position in
+    /// `other`. Panics if the canvases differ in size.
+    pub fn diff_count(&self, other: &Canvas) -> usize {
+        assert!(
+            self.w() == other.w() && self.h() == other.h(),
+            "Canvas size ({}, {}) does not match ({}, {})",
+            self.w(),
+            self.h(),
+            other.w(),
+            other.h()
+        );
+
+        self.iter_pixels()
+            .zip(other.iter_pixels())
+            .filter(|((_, _, a), (_, _, b))| !a.float_eq(b))
+            .count()
+    }
+}
+
+impl FloatEq for Canvas {
+    fn float_eq(&self, other: &Self) -> bool {
+        self.float_eq_within(other, f64::EPSILON)
+    }
+
+    fn float_eq_within(&self, other: &Self, eps: f64) -> bool {
+        self.w() == other.w()
+            && self.h() == other.h()
+            && self
+                .iter_pixels()
+                .zip(other.iter_pixels())
+                .all(|((_, _, a), (_, _, b))| a.float_eq_within(&b, eps))
+    }
+}
+
+/// Turns a row-major depth buffer (`t` of the nearest hit per pixel) into a grayscale
+/// canvas. Depths at `near` or closer are white, depths at `far` or further are black,
+/// and so are misses (any non-finite depth, e.g. infinity or NaN). Panics unless
+/// `far > near`.
+pub fn depth_to_canvas(depths: &[f64], w: usize, near: f64, far: f64) -> Canvas {
+    assert!(
+        far > near,
+        "Far depth {} must be greater than near depth {}",
+        far,
+        near
+    );
+    assert!(
+        w > 0 && depths.len() % w == 0,
+        "Depth buffer of length {} cannot be split into rows of width {}",
+        depths.len(),
+        w
+    );
+
+    let mut canvas = Canvas::new(w, depths.len() / w);
+    depths.iter().enumerate().for_each(|(i, depth)| {
+        let v = if depth.is_finite() {
+            1.0 - ((depth - near) / (far - near)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        canvas.write_px(i % w, i / w, Color::new(v, v, v));
+    });
+    canvas
+}
+
+/// Running sum of rendered passes, used to progressively refine an image by averaging
+/// more and more samples per pixel
+pub struct AccumulationBuffer {
+    w: usize,
+    h: usize,
+    sum: Vec<Color>,
+    samples: usize,
+}
+
+impl AccumulationBuffer {
+    pub fn new(w: usize, h: usize) -> Self {
+        Self {
+            w,
+            h,
+            sum: vec![Color::BLACK; w * h],
+            samples: 0,
+        }
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Panics if the pass does not have the same size as the buffer
+    pub fn add_pass(&mut self, pass: &Canvas) {
+        assert!(
+            pass.w() == self.w && pass.h() == self.h,
+            "Pass of size ({}, {}) does not match buffer of size ({}, {})",
+            pass.w(),
+            pass.h(),
+            self.w,
+            self.h
+        );
+
+        pass.iter_pixels().for_each(|(x, y, color)| {
+            self.sum[y * self.w + x] = self.sum[y * self.w + x] + color;
+        });
+        self.samples += 1;
+    }
+
+    /// Average of all passes so far (black if there are none)
+    pub fn to_canvas(&self) -> Canvas {
+        let scale = if self.samples == 0 {
+            0.0
+        } else {
+            1.0 / self.samples as f64
+        };
+
+        Canvas::new(self.w, self.h).map_pixels(|x, y, _| self.sum[y * self.w + x] * scale)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::math::assert_float_eq;
+    use crate::math::{assert_float_eq, assert_float_eq_within};
 
     use super::*;
 
@@ -201,6 +812,102 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_color_mul_saturating() {
+        let bright = Color::new(0.8, 0.8, 0.8);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let c = bright.mul_saturating(&white);
+        assert!(c.r() <= 1.0 && c.g() <= 1.0 && c.b() <= 1.0);
+        assert_float_eq(c, bright * white);
+
+        assert_float_eq(
+            Color::new(1.5, 2.0, -0.5).mul_saturating(&Color::new(1.2, 0.25, 1.0)),
+            Color::new(1.0, 0.5, 0.0),
+        );
+        assert_float_eq(
+            Color::new(0.8, 0.2, -0.1).mul_scalar_saturating(2.0),
+            Color::new(1.0, 0.4, 0.0),
+        );
+        // the linear product is left untouched
+        assert_float_eq(Color::new(0.8, 0.2, 0.1) * 2.0, Color::new(1.6, 0.4, 0.2));
+    }
+
+    #[test]
+    fn test_color_tone_map() {
+        let in_unit_range = |c: Color| c.vals.iter().all(|v| (0.0..=1.0).contains(v));
+
+        let bright = Color::new(4.0, 2.0, 1.0);
+        [bright.tone_map_reinhard(), bright.tone_map_aces()]
+            .into_iter()
+            .for_each(|c| {
+                assert!(in_unit_range(c), "{:?}", c);
+                assert!(c.r() > c.g() && c.g() > c.b(), "{:?}", c);
+            });
+        assert_float_eq(bright.tone_map_reinhard(), Color::new(0.8, 2.0 / 3.0, 0.5));
+
+        let dim = Color::new(0.02, 0.01, 0.005);
+        assert_float_eq_within(dim.tone_map_reinhard(), dim, 1e-3);
+
+        assert_float_eq(Color::BLACK.tone_map_reinhard(), Color::BLACK);
+        assert_float_eq(Color::new(-1.0, 0.0, 0.0).tone_map_aces(), Color::BLACK);
+        assert!(in_unit_range(Color::new(1e6, 1e3, 0.5).tone_map_aces()));
+    }
+
+    #[test]
+    fn test_color_hsv() {
+        assert_float_eq(Color::from_hsv(0.0, 1.0, 1.0), Color::new(1.0, 0.0, 0.0));
+        assert_float_eq(Color::from_hsv(120.0, 1.0, 1.0), Color::new(0.0, 1.0, 0.0));
+        assert_float_eq(Color::from_hsv(240.0, 1.0, 1.0), Color::new(0.0, 0.0, 1.0));
+        assert_float_eq(Color::from_hsv(360.0, 1.0, 1.0), Color::new(1.0, 0.0, 0.0));
+        assert_float_eq(Color::from_hsv(60.0, 0.0, 0.5), Color::new(0.5, 0.5, 0.5));
+
+        let c = Color::from_hsv(200.0, 0.5, 0.8);
+        assert_float_eq(c, Color::new(0.4, 0.6666666666666667, 0.8));
+        let (h, s, v) = c.to_hsv();
+        assert_float_eq(h, 200.0);
+        assert_float_eq(s, 0.5);
+        assert_float_eq(v, 0.8);
+        assert_float_eq(Color::from_hsv(h, s, v), c);
+
+        assert_eq!(Color::new(0.3, 0.3, 0.3).to_hsv(), (0.0, 0.0, 0.3));
+    }
+
+    #[test]
+    fn test_color_luminance() {
+        assert_float_eq(Color::WHITE.luminance(), 1.0);
+        assert_float_eq(Color::BLACK.luminance(), 0.0);
+        assert_float_eq(Color::new(0.0, 1.0, 0.0).luminance(), 0.7152);
+
+        let gray = Color::new(1.0, 0.2, 0.5).grayscale();
+        assert_float_eq(gray.r(), gray.g());
+        assert_float_eq(gray.g(), gray.b());
+        assert_float_eq(gray.r(), 0.2126 + 0.7152 * 0.2 + 0.0722 * 0.5);
+    }
+
+    #[test]
+    fn test_color_lerp() {
+        let a = Color::BLACK;
+        let b = Color::new(1.0, 0.5, 0.2);
+        assert_float_eq(a.lerp(&b, 0.0), a);
+        assert_float_eq(a.lerp(&b, 1.0), b);
+        assert_float_eq(a.lerp(&b, 0.5), Color::new(0.5, 0.25, 0.1));
+    }
+
+    #[test]
+    fn test_color_from_tuple() {
+        let c: Color = (0.5, 0.25, 1.0).into();
+        assert_eq!(c, Color::new(0.5, 0.25, 1.0));
+        assert_eq!(Color::from((0.0, 0.0, 0.0)), Color::BLACK);
+    }
+
+    #[test]
+    fn test_color_display() {
+        assert_eq!(
+            Color::new(1.0, 0.5, -0.25).to_string(),
+            "rgb(1, 0.5, -0.25)"
+        );
+    }
+
     #[test]
     fn test_canvas_new() {
         let c = Canvas::new(10, 20);
@@ -213,6 +920,67 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_canvas_from_fn() {
+        let black = Color::BLACK;
+        let white = Color::WHITE;
+        let c = Canvas::from_fn(4, 4, |x, y| if (x + y) % 2 == 0 { white } else { black });
+
+        assert_eq!((c.w(), c.h()), (4, 4));
+        c.iter_pixels().for_each(|(x, y, color)| {
+            let expected = if (x + y) % 2 == 0 { white } else { black };
+            assert_eq!(color, expected);
+        });
+        assert_eq!(c.px(0, 0), white);
+        assert_eq!(c.px(1, 0), black);
+        assert_eq!(c.px(1, 1), white);
+
+        let c = Canvas::from_fn(3, 2, |x, y| Color::new(x as f64, y as f64, 0.0));
+        assert_eq!(c.px(2, 1), Color::new(2.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_canvas_empty() {
+        let c = Canvas::new(0, 5);
+        assert_eq!(c.w(), 0);
+        assert_eq!(c.h(), 5);
+
+        let c = Canvas::new(5, 0);
+        assert_eq!(c.w(), 5);
+        assert_eq!(c.h(), 0);
+    }
+
+    #[test]
+    fn test_canvas_try_new() {
+        assert!(Canvas::try_new(10, 20).is_ok());
+        assert_eq!(
+            Canvas::try_new(usize::MAX, 2).err(),
+            Some(CanvasError::TooLarge {
+                w: usize::MAX,
+                h: 2
+            })
+        );
+        assert!(Canvas::try_new(usize::MAX / 2, usize::MAX / 2).is_err());
+
+        // empty rows still need a row header each
+        assert_eq!(
+            Canvas::try_new(0, usize::MAX).err(),
+            Some(CanvasError::TooLarge {
+                w: 0,
+                h: usize::MAX
+            })
+        );
+        assert!(Canvas::try_new(0, usize::MAX / 64).is_err());
+        assert!(Canvas::try_new(0, 5).is_ok());
+        assert!(Canvas::try_new(5, 0).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "is too large")]
+    fn test_canvas_new_too_large() {
+        Canvas::new(usize::MAX, usize::MAX);
+    }
+
     #[test]
     fn test_canvas_write() {
         let mut c = Canvas::new(10, 20);
@@ -220,4 +988,504 @@ mod tests {
         c.write_px(2, 3, red);
         assert_float_eq(c.px(2, 3), red);
     }
+
+    #[test]
+    fn test_canvas_sample_uv() {
+        let mut c = Canvas::new(2, 2);
+        c.write_px(0, 0, Color::new(1.0, 0.0, 0.0));
+        c.write_px(1, 0, Color::new(0.0, 1.0, 0.0));
+        c.write_px(0, 1, Color::new(0.0, 0.0, 1.0));
+        c.write_px(1, 1, Color::new(1.0, 1.0, 1.0));
+
+        assert_float_eq(c.sample_uv(0.0, 0.0, WrapMode::Clamp), c.px(0, 0));
+        assert_float_eq(c.sample_uv(0.25, 0.25, WrapMode::Clamp), c.px(0, 0));
+        assert_float_eq(c.sample_uv(1.0, 1.0, WrapMode::Clamp), c.px(1, 1));
+        assert_float_eq(c.sample_uv(0.75, 0.25, WrapMode::Repeat), c.px(1, 0));
+        assert_float_eq(
+            c.sample_uv(0.5, 0.5, WrapMode::Clamp),
+            Color::new(0.5, 0.5, 0.5),
+        );
+        assert_float_eq(
+            c.sample_uv(0.5, 0.25, WrapMode::Clamp),
+            Color::new(0.5, 0.5, 0.0),
+        );
+
+        assert_float_eq(
+            c.sample_uv(1.25, 0.25, WrapMode::Repeat),
+            c.sample_uv(0.25, 0.25, WrapMode::Repeat),
+        );
+        assert_float_eq(
+            c.sample_uv(-0.75, 1.75, WrapMode::Repeat),
+            c.sample_uv(0.25, 0.75, WrapMode::Repeat),
+        );
+        // at the edge, repeat blends with the opposite side while clamp does not
+        assert_float_eq(
+            c.sample_uv(0.0, 0.25, WrapMode::Repeat),
+            Color::new(0.5, 0.5, 0.0),
+        );
+        assert_float_eq(c.sample_uv(1.25, 0.25, WrapMode::Clamp), c.px(1, 0));
+
+        [Canvas::new(0, 2), Canvas::new(2, 0)]
+            .into_iter()
+            .for_each(|empty| {
+                assert_eq!(empty.sample_uv(0.5, 0.5, WrapMode::Clamp), Color::BLACK);
+                assert_eq!(empty.sample_uv(0.5, 0.5, WrapMode::Repeat), Color::BLACK);
+            });
+    }
+
+    #[test]
+    fn test_canvas_to_rgb8() {
+        let mut c = Canvas::new(2, 2);
+        c.write_px(0, 0, Color::new(1.5, 0.0, 0.0));
+        c.write_px(1, 0, Color::new(0.0, 0.5, 0.0));
+        c.write_px(0, 1, Color::new(-0.5, 0.0, 1.0));
+        c.write_px(1, 1, Color::new(0.2, 0.4, 0.6));
+
+        assert_eq!(
+            c.to_rgb8(),
+            vec![255, 0, 0, 0, 128, 0, 0, 0, 255, 51, 102, 153]
+        );
+        assert_eq!(
+            c.to_rgba8(),
+            vec![255, 0, 0, 255, 0, 128, 0, 255, 0, 0, 255, 255, 51, 102, 153, 255]
+        );
+    }
+
+    #[test]
+    fn test_canvas_iter_pixels() {
+        let c = gradient_canvas(3, 2);
+        let pixels = c.iter_pixels().collect::<Vec<_>>();
+
+        assert_eq!(pixels.len(), 6);
+        assert_eq!(pixels[0], (0, 0, c.px(0, 0)));
+        assert_eq!(pixels[4], (1, 1, c.px(1, 1)));
+        assert!(pixels.iter().all(|(x, y, color)| c.px(*x, *y) == *color));
+    }
+
+    #[test]
+    fn test_canvas_map_pixels() {
+        let c = gradient_canvas(3, 2);
+        let gray = c.map_pixels(|_, _, color| color.grayscale());
+
+        assert_eq!(gray.w(), 3);
+        assert_eq!(gray.h(), 2);
+        c.iter_pixels().for_each(|(x, y, color)| {
+            assert_float_eq(gray.px(x, y), color.grayscale());
+        });
+
+        let coords = c.map_pixels(|x, y, _| Color::new(x as f64, y as f64, 0.0));
+        assert_float_eq(coords.px(2, 1), Color::new(2.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_canvas_draw_line() {
+        let white = Color::WHITE;
+        let mut c = Canvas::new(5, 5);
+        c.draw_line((0, 0), (4, 2), white);
+
+        let lit = c
+            .iter_pixels()
+            .filter(|(_, _, color)| *color == white)
+            .map(|(x, y, _)| (x, y))
+            .collect::<Vec<_>>();
+        assert_eq!(lit, vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)]);
+
+        // clipped at the edges, same pixels whichever way it is drawn
+        let mut a = Canvas::new(5, 5);
+        a.draw_line((-3, 2), (8, 2), white);
+        let mut b = Canvas::new(5, 5);
+        b.draw_line((8, 2), (-3, 2), white);
+        assert_eq!(a, b);
+        assert!((0..5).all(|x| a.px(x, 2) == white));
+        assert_eq!(a.iter_pixels().filter(|(_, _, c)| *c == white).count(), 5);
+
+        // far away endpoints are clipped before drawing
+        let mut c = Canvas::new(5, 5);
+        c.draw_line((0, 0), (isize::MAX / 4, 0), white);
+        c.draw_line((2, 2), (isize::MIN / 4, isize::MIN / 4), white);
+        let lit = c
+            .iter_pixels()
+            .filter(|(_, _, color)| *color == white)
+            .map(|(x, y, _)| (x, y))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lit,
+            vec![(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (1, 1), (2, 2)]
+        );
+
+        // misses the canvas entirely
+        let mut c = Canvas::new(5, 5);
+        c.draw_line((-10, -1), (isize::MAX / 4, -1), white);
+        c.draw_line((-10, 10), (10, 30), white);
+        assert!(c.iter_pixels().all(|(_, _, color)| color == Color::BLACK));
+        Canvas::new(0, 0).draw_line((0, 0), (5, 5), white);
+    }
+
+    #[test]
+    fn test_canvas_draw_polygon() {
+        let white = Color::WHITE;
+        let triangle = [(1, 1), (9, 1), (5, 9)];
+        let mut c = Canvas::new(11, 11);
+        c.draw_polygon(&triangle, white);
+
+        // vertices, and the midpoint of each edge
+        [(1, 1), (9, 1), (5, 9), (5, 1), (7, 5), (3, 5)]
+            .into_iter()
+            .for_each(|(x, y)| assert_eq!(c.px(x, y), white, "({}, {})", x, y));
+        assert_eq!(c.px(5, 4), Color::BLACK);
+
+        // a polyline leaves the last edge open
+        let mut c = Canvas::new(11, 11);
+        c.draw_polyline(&triangle, white);
+        assert_eq!(c.px(5, 1), white);
+        assert_eq!(c.px(7, 5), white);
+        assert_eq!(c.px(3, 5), Color::BLACK);
+
+        let mut c = Canvas::new(3, 3);
+        c.draw_polygon(&[], white);
+        c.draw_polygon(&[(1, 1)], white);
+        assert_eq!(c.iter_pixels().filter(|(_, _, c)| *c == white).count(), 1);
+    }
+
+    #[test]
+    fn test_canvas_draw_circle() {
+        let white = Color::WHITE;
+        let mut c = Canvas::new(11, 11);
+        c.draw_circle(5, 5, 3, white);
+
+        [
+            (8, 5),
+            (5, 8),
+            (2, 5),
+            (5, 2),
+            (7, 7),
+            (3, 7),
+            (3, 3),
+            (7, 3),
+        ]
+        .into_iter()
+        .for_each(|(x, y)| assert_eq!(c.px(x, y), white, "({}, {})", x, y));
+        assert_eq!(c.px(5, 5), Color::BLACK);
+        assert_eq!(c.px(9, 5), Color::BLACK);
+
+        // symmetric in all eight octants
+        (0..11).for_each(|y| {
+            (0..11).for_each(|x| {
+                assert_eq!(c.px(x, y), c.px(y, x));
+                assert_eq!(c.px(x, y), c.px(10 - x, y));
+                assert_eq!(c.px(x, y), c.px(x, 10 - y));
+            });
+        });
+    }
+
+    #[test]
+    fn test_canvas_draw_circle_clipped() {
+        let white = Color::WHITE;
+        let mut c = Canvas::new(5, 5);
+        c.draw_circle(0, 0, 3, white);
+        c.draw_circle(-10, 2, 3, white);
+
+        [(3, 0), (3, 1), (2, 2), (1, 3), (0, 3)]
+            .into_iter()
+            .for_each(|(x, y)| assert_eq!(c.px(x, y), white));
+        assert_eq!(c.px(0, 0), Color::BLACK);
+        assert_eq!(
+            (0..5)
+                .flat_map(|y| (0..5).map(move |x| (x, y)))
+                .filter(|(x, y)| c.px(*x, *y) == white)
+                .count(),
+            5
+        );
+    }
+
+    #[test]
+    fn test_canvas_fill_circle() {
+        let white = Color::WHITE;
+        let mut c = Canvas::new(5, 5);
+        c.fill_circle(4, 4, 1, white);
+
+        [(4, 4), (3, 4), (4, 3)]
+            .into_iter()
+            .for_each(|(x, y)| assert_eq!(c.px(x, y), white));
+        assert_eq!(c.px(3, 3), Color::BLACK);
+        assert_eq!(c.px(2, 4), Color::BLACK);
+    }
+
+    fn gradient_canvas(w: usize, h: usize) -> Canvas {
+        Canvas::from_fn(w, h, |x, y| {
+            Color::new(x as f64 / w as f64, y as f64 / h as f64, 0.0)
+        })
+    }
+
+    #[test]
+    fn test_canvas_crop() {
+        let c = gradient_canvas(4, 4);
+        let cropped = c.crop(1, 1, 2, 2);
+
+        assert_eq!(cropped.w(), 2);
+        assert_eq!(cropped.h(), 2);
+        (0..2).for_each(|y| {
+            (0..2).for_each(|x| {
+                assert_float_eq(cropped.px(x, y), c.px(x + 1, y + 1));
+            });
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_canvas_crop_out_of_range() {
+        gradient_canvas(4, 4).crop(3, 0, 2, 2);
+    }
+
+    #[test]
+    fn test_canvas_resize_nearest() {
+        let c = gradient_canvas(4, 4);
+
+        let small = c.resize_nearest(2, 2);
+        assert_eq!((small.w(), small.h()), (2, 2));
+        assert_float_eq(small.px(0, 0), c.px(0, 0));
+        assert_float_eq(small.px(1, 0), c.px(2, 0));
+        assert_float_eq(small.px(0, 1), c.px(0, 2));
+        assert_float_eq(small.px(1, 1), c.px(2, 2));
+
+        let big = small.resize_nearest(4, 4);
+        assert_eq!((big.w(), big.h()), (4, 4));
+        c.iter_pixels().for_each(|(x, y, _)| {
+            assert_float_eq(big.px(x, y), small.px(x / 2, y / 2));
+        });
+
+        let same = c.resize_nearest(4, 4);
+        c.iter_pixels()
+            .for_each(|(x, y, color)| assert_eq!(same.px(x, y), color));
+
+        assert_eq!(Canvas::new(0, 3).resize_nearest(2, 2), Canvas::new(2, 2));
+        assert_eq!(Canvas::new(3, 0).resize_nearest(2, 1), Canvas::new(2, 1));
+        assert_eq!(c.resize_nearest(0, 0), Canvas::new(0, 0));
+    }
+
+    #[test]
+    fn test_canvas_flip() {
+        let c = gradient_canvas(3, 2);
+
+        let v = c.flip_vertical();
+        assert_float_eq(v.px(0, 0), c.px(0, 1));
+        assert_float_eq(v.px(2, 1), c.px(2, 0));
+
+        let h = c.flip_horizontal();
+        assert_float_eq(h.px(0, 0), c.px(2, 0));
+        assert_float_eq(h.px(1, 1), c.px(1, 1));
+
+        [
+            c.flip_vertical().flip_vertical(),
+            c.flip_horizontal().flip_horizontal(),
+        ]
+        .into_iter()
+        .for_each(|flipped| {
+            (0..2).for_each(|y| {
+                (0..3).for_each(|x| assert_float_eq(flipped.px(x, y), c.px(x, y)));
+            });
+        });
+    }
+
+    #[test]
+    fn test_canvas_tiles() {
+        let c = gradient_canvas(10, 7);
+        let tiles = c.tiles(4, 4).collect::<Vec<_>>();
+        assert_eq!(
+            tiles,
+            vec![
+                TileRegion {
+                    x: 0,
+                    y: 0,
+                    w: 4,
+                    h: 4
+                },
+                TileRegion {
+                    x: 4,
+                    y: 0,
+                    w: 4,
+                    h: 4
+                },
+                TileRegion {
+                    x: 8,
+                    y: 0,
+                    w: 2,
+                    h: 4
+                },
+                TileRegion {
+                    x: 0,
+                    y: 4,
+                    w: 4,
+                    h: 3
+                },
+                TileRegion {
+                    x: 4,
+                    y: 4,
+                    w: 4,
+                    h: 3
+                },
+                TileRegion {
+                    x: 8,
+                    y: 4,
+                    w: 2,
+                    h: 3
+                },
+            ]
+        );
+        assert_eq!(Canvas::new(0, 5).tiles(2, 2).count(), 0);
+
+        // reassembling the tiles gives back the whole canvas
+        let c = gradient_canvas(10, 10);
+        let mut assembled = Canvas::new(10, 10);
+        c.tiles(5, 5).for_each(|t| {
+            assembled.blit(&c.crop(t.x, t.y, t.w, t.h), t.x, t.y);
+        });
+        assert_eq!(c.tiles(5, 5).count(), 4);
+        assert_eq!(assembled, c);
+    }
+
+    #[test]
+    #[should_panic(expected = "must not be empty")]
+    fn test_canvas_tiles_empty() {
+        let _ = Canvas::new(2, 2).tiles(0, 1);
+    }
+
+    #[test]
+    fn test_canvas_overlay() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let mut dst = Canvas::new(4, 4).map_pixels(|_, _, _| blue);
+        let src = Canvas::new(3, 3).map_pixels(|_, _, _| red);
+
+        dst.overlay(&src, 2, 1, 0.5);
+        dst.iter_pixels().for_each(|(x, y, c)| {
+            if x >= 2 && (1..4).contains(&y) {
+                assert_float_eq(c, Color::new(0.5, 0.0, 0.5));
+            } else {
+                assert_float_eq(c, blue);
+            }
+        });
+
+        let mut dst = Canvas::new(2, 2).map_pixels(|_, _, _| blue);
+        dst.overlay(&src, 0, 0, 1.0);
+        assert!(dst.float_eq(&Canvas::new(2, 2).map_pixels(|_, _, _| red)));
+        dst.overlay(&Canvas::new(2, 2), 0, 0, 0.0);
+        assert_float_eq(dst.px(1, 1), red);
+    }
+
+    #[test]
+    fn test_canvas_float_eq() {
+        let a = gradient_canvas(4, 3);
+        let mut b = gradient_canvas(4, 3);
+        assert!(a.float_eq(&b));
+        assert_eq!(a.diff_count(&b), 0);
+
+        // within tolerance
+        b.write_px(0, 0, a.px(0, 0) + Color::new(f64::EPSILON / 2.0, 0.0, 0.0));
+        assert!(a.float_eq(&b));
+
+        b.write_px(1, 1, Color::new(0.3, 0.3, 0.3));
+        b.write_px(3, 2, Color::new(1.0, 0.0, 1.0));
+        assert!(!a.float_eq(&b));
+        assert_eq!(a.diff_count(&b), 2);
+
+        assert!(!a.float_eq(&gradient_canvas(3, 4)));
+        assert!(!a.float_eq(&gradient_canvas(4, 4)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Canvas size (4, 3) does not match (3, 4)")]
+    fn test_canvas_diff_count_size_mismatch() {
+        gradient_canvas(4, 3).diff_count(&gradient_canvas(3, 4));
+    }
+
+    #[test]
+    fn test_canvas_blit() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let mut src = Canvas::new(2, 2);
+        (0..2).for_each(|y| (0..2).for_each(|x| src.write_px(x, y, red)));
+
+        let mut c = Canvas::new(5, 5);
+        c.blit(&src, 1, 1);
+        (0..5).for_each(|y| {
+            (0..5).for_each(|x| {
+                let expected = if (1..3).contains(&x) && (1..3).contains(&y) {
+                    red
+                } else {
+                    Color::BLACK
+                };
+                assert_float_eq(c.px(x, y), expected);
+            });
+        });
+
+        let mut c = Canvas::new(5, 5);
+        c.blit(&src, 4, 4);
+        assert_float_eq(c.px(4, 4), red);
+        assert_float_eq(c.px(3, 3), Color::BLACK);
+
+        let mut c = Canvas::new(5, 5);
+        c.blit(&src, 5, 0);
+        assert!((0..5).all(|y| (0..5).all(|x| c.px(x, y) == Color::BLACK)));
+    }
+
+    #[test]
+    fn test_depth_to_canvas() {
+        let depths = [1.0, 2.0, 3.0, 0.5, 4.0, f64::INFINITY];
+        let c = depth_to_canvas(&depths, 3, 1.0, 3.0);
+
+        assert_eq!((c.w(), c.h()), (3, 2));
+        assert_float_eq(c.px(0, 0), Color::new(1.0, 1.0, 1.0));
+        assert_float_eq(c.px(1, 0), Color::new(0.5, 0.5, 0.5));
+        assert_float_eq(c.px(2, 0), Color::new(0.0, 0.0, 0.0));
+        assert_float_eq(c.px(0, 1), Color::new(1.0, 1.0, 1.0));
+        assert_float_eq(c.px(1, 1), Color::new(0.0, 0.0, 0.0));
+        assert_float_eq(c.px(2, 1), Color::new(0.0, 0.0, 0.0));
+
+        let c = depth_to_canvas(&[f64::NAN, f64::NEG_INFINITY], 2, 1.0, 3.0);
+        assert_eq!(c.px(0, 0), Color::BLACK);
+        assert_eq!(c.px(1, 0), Color::BLACK);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be split into rows")]
+    fn test_depth_to_canvas_bad_width() {
+        depth_to_canvas(&[1.0, 2.0, 3.0], 2, 0.0, 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be greater than near")]
+    fn test_depth_to_canvas_empty_range() {
+        depth_to_canvas(&[1.0, 2.0], 2, 1.0, 1.0);
+    }
+
+    #[test]
+    fn test_accumulation_buffer() {
+        let mut buffer = AccumulationBuffer::new(3, 2);
+        assert_eq!(buffer.samples(), 0);
+        assert!(buffer
+            .to_canvas()
+            .iter_pixels()
+            .all(|(_, _, c)| c == Color::BLACK));
+
+        let pass = gradient_canvas(3, 2);
+        (0..5).for_each(|_| buffer.add_pass(&pass));
+        assert_eq!(buffer.samples(), 5);
+        pass.iter_pixels().for_each(|(x, y, color)| {
+            assert_float_eq(buffer.to_canvas().px(x, y), color);
+        });
+
+        let mut buffer = AccumulationBuffer::new(1, 1);
+        [0.2, 0.4, 0.9].into_iter().for_each(|v| {
+            let mut pass = Canvas::new(1, 1);
+            pass.write_px(0, 0, Color::new(v, v, v));
+            buffer.add_pass(&pass);
+        });
+        assert_float_eq(buffer.to_canvas().px(0, 0), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_accumulation_buffer_size_mismatch() {
+        AccumulationBuffer::new(3, 2).add_pass(&Canvas::new(2, 3));
+    }
 }
@@ -1,59 +1,252 @@
-use crate::graphics::Canvas;
+use std::{
+    fmt::{self, Write},
+    fs, io,
+    path::Path,
+};
+
+use crate::graphics::{Canvas, CanvasError, Color};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DitherMode {
+    /// Plain rounding to the nearest byte value
+    None,
+    /// Ordered dithering with a 4x4 Bayer matrix
+    Ordered,
+    /// Floyd–Steinberg error diffusion
+    FloydSteinberg,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PpmOptions {
+    /// Longest allowed line in the pixel data, `None` writes each scanline on one line.
+    /// Values are never split, so a line only goes over the limit if a single value
+    /// does.
+    pub max_line_len: Option<usize>,
+}
+
+impl Default for PpmOptions {
+    /// 70 characters, as recommended by the PPM specification
+    fn default() -> Self {
+        Self {
+            max_line_len: Some(70),
+        }
+    }
+}
+
+const BAYER_4X4: [[f64; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
 
 pub fn canvas_to_ppm(canvas: &Canvas) -> String {
-    let mut output = vec![];
+    canvas_to_ppm_with_options(canvas, PpmOptions::default())
+}
 
-    output.push("P3".to_string());
-    output.push(format!("{} {}", canvas.w(), canvas.h()));
-    output.push("255".to_string());
+pub fn canvas_to_ppm_with_options(canvas: &Canvas, options: PpmOptions) -> String {
+    ppm_from_rgb8(canvas.w(), canvas.h(), &canvas.to_rgb8(), options)
+}
 
-    (0..canvas.h()).for_each(|y| {
-        let mut current = String::new();
+/// Writes the canvas to `path`, in the format given by its extension. Only `.ppm` is
+/// supported for now, anything else is an `Unsupported` error. `.png` is deferred until
+/// the crate gets a `png` feature.
+pub fn write_image(canvas: &Canvas, path: &Path) -> io::Result<()> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("ppm") => fs::write(path, canvas_to_ppm(canvas)),
+        Some(ext) if ext.eq_ignore_ascii_case("png") => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "PNG output needs the `png` feature, which is not available yet: {}",
+                path.display()
+            ),
+        )),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Unsupported image format: {}", path.display()),
+        )),
+    }
+}
 
-        (0..canvas.w())
-            .flat_map(|x| {
-                let c = canvas.px(x, y);
+#[derive(Debug, PartialEq, Clone)]
+pub enum PpmError {
+    /// The data does not start with `P3`, only plain PPMs are supported.
+    NotPlainPpm,
+    /// A header field or color value that is not a valid number.
+    InvalidNumber(String),
+    /// The maximum color value in the header is 0.
+    ZeroMaxValue,
+    /// The data ended before every pixel was read.
+    UnexpectedEnd,
+    /// There is more data after the last pixel.
+    TrailingData,
+    Canvas(CanvasError),
+}
 
-                fn to_int(v: f64) -> i32 {
-                    if v < 0.0 {
-                        0
-                    } else if v > 1.0 {
-                        255
-                    } else {
-                        (v * 255.0).round() as i32
-                    }
-                }
+impl fmt::Display for PpmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PpmError::NotPlainPpm => write!(f, "Not a plain (P3) PPM"),
+            PpmError::InvalidNumber(token) => write!(f, "Invalid number in PPM: {}", token),
+            PpmError::ZeroMaxValue => write!(f, "PPM maximum color value must not be 0"),
+            PpmError::UnexpectedEnd => write!(f, "PPM ended before all pixels were read"),
+            PpmError::TrailingData => write!(f, "PPM has data after the last pixel"),
+            PpmError::Canvas(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for PpmError {}
+
+/// Reads a plain (P3) PPM, such as the ones written by `canvas_to_ppm()`. Color values
+/// are scaled by the maximum value in the header, and `#` comments are skipped.
+pub fn canvas_from_ppm(ppm: &str) -> Result<Canvas, PpmError> {
+    let mut tokens = ppm
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(str::split_whitespace);
+
+    if tokens.next() != Some("P3") {
+        return Err(PpmError::NotPlainPpm);
+    }
+
+    let mut next_number = || {
+        let token = tokens.next().ok_or(PpmError::UnexpectedEnd)?;
+        token
+            .parse::<usize>()
+            .map_err(|_| PpmError::InvalidNumber(token.to_string()))
+    };
+    let (w, h, max_value) = (next_number()?, next_number()?, next_number()?);
+    if max_value == 0 {
+        return Err(PpmError::ZeroMaxValue);
+    }
 
-                [to_int(c.r()), to_int(c.g()), to_int(c.b())]
+    let mut canvas = Canvas::try_new(w, h).map_err(PpmError::Canvas)?;
+    for y in 0..h {
+        for x in 0..w {
+            let mut channel = || next_number().map(|v| v as f64 / max_value as f64);
+            let color = Color::new(channel()?, channel()?, channel()?);
+            canvas.write_px(x, y, color);
+        }
+    }
+
+    if tokens.next().is_some() {
+        return Err(PpmError::TrailingData);
+    }
+    Ok(canvas)
+}
+
+/// Same as `canvas_to_ppm()`, but gamma-encodes each color component (`v^(1/gamma)`)
+/// before writing it out. A gamma of 2.2 gives roughly sRGB output.
+pub fn canvas_to_ppm_gamma(canvas: &Canvas, gamma: f64) -> String {
+    let encode = |v: f64| v.clamp(0.0, 1.0).powf(1.0 / gamma);
+    let bytes = canvas
+        .iter_pixels()
+        .flat_map(|(_, _, c)| Color::new(encode(c.r()), encode(c.g()), encode(c.b())).to_rgb8())
+        .collect::<Vec<_>>();
+
+    ppm_from_rgb8(canvas.w(), canvas.h(), &bytes, PpmOptions::default())
+}
+
+/// Same as `canvas_to_ppm()`, but dithers each color component while quantizing it to
+/// 8 bits, which hides banding in smooth gradients.
+pub fn canvas_to_ppm_dithered(canvas: &Canvas, mode: DitherMode) -> String {
+    let bytes = match mode {
+        DitherMode::None => canvas.to_rgb8(),
+        DitherMode::Ordered => canvas
+            .iter_pixels()
+            .flat_map(|(x, y, c)| {
+                let threshold = (BAYER_4X4[y % 4][x % 4] + 0.5) / 16.0;
+                [c.r(), c.g(), c.b()]
+                    .map(|v| (v.clamp(0.0, 1.0) * 255.0 + threshold).floor().min(255.0) as u8)
             })
-            .for_each(|v| {
-                if current.is_empty() {
-                    current += &v.to_string();
-                } else {
-                    let v_str = format!(" {}", v);
-                    if current.len() + v_str.len() <= 70 {
-                        current += &v_str;
-                    } else {
-                        output.push(current.to_string());
-                        current = v.to_string();
+            .collect(),
+        DitherMode::FloydSteinberg => floyd_steinberg(canvas),
+    };
+
+    ppm_from_rgb8(canvas.w(), canvas.h(), &bytes, PpmOptions::default())
+}
+
+fn floyd_steinberg(canvas: &Canvas) -> Vec<u8> {
+    let (w, h) = (canvas.w(), canvas.h());
+    let mut vals = canvas
+        .iter_pixels()
+        .flat_map(|(_, _, c)| [c.r(), c.g(), c.b()].map(|v| v.clamp(0.0, 1.0) * 255.0))
+        .collect::<Vec<_>>();
+    let mut bytes = vec![0; vals.len()];
+
+    (0..h).for_each(|y| {
+        (0..w).for_each(|x| {
+            (0..3).for_each(|ch| {
+                let i = (y * w + x) * 3 + ch;
+                let quantized = vals[i].round().clamp(0.0, 255.0);
+                let error = vals[i] - quantized;
+                bytes[i] = quantized as u8;
+
+                let mut spread = |dx: isize, dy: usize, weight: f64| {
+                    let nx = x as isize + dx;
+                    let ny = y + dy;
+                    if nx >= 0 && (nx as usize) < w && ny < h {
+                        vals[(ny * w + nx as usize) * 3 + ch] += error * weight;
                     }
-                }
+                };
+                spread(1, 0, 7.0 / 16.0);
+                spread(-1, 1, 3.0 / 16.0);
+                spread(0, 1, 5.0 / 16.0);
+                spread(1, 1, 1.0 / 16.0);
             });
+        });
+    });
+
+    bytes
+}
+
+/// Writes out row-major RGB bytes as a PPM. Values are formatted straight into the
+/// output, without allocating a string per value.
+fn ppm_from_rgb8(w: usize, h: usize, bytes: &[u8], options: PpmOptions) -> String {
+    let max_line_len = options.max_line_len.unwrap_or(usize::MAX);
+    // at most 4 characters per value ("255" and a separator), plus the header
+    let mut output = String::with_capacity(bytes.len() * 4 + 32);
+
+    writeln!(output, "P3\n{} {}\n255", w, h).unwrap();
+
+    // `max(1)` keeps `chunks()` happy for zero-width canvases, which have no bytes anyway
+    bytes.chunks((w * 3).max(1)).for_each(|row| {
+        let mut line_len = 0;
+
+        row.iter().for_each(|&v| {
+            let v_len = match v {
+                100.. => 3,
+                10.. => 2,
+                _ => 1,
+            };
 
-        if !current.is_empty() {
-            output.push(current);
+            if line_len > 0 {
+                if line_len + 1 + v_len <= max_line_len {
+                    output.push(' ');
+                    line_len += 1;
+                } else {
+                    output.push('\n');
+                    line_len = 0;
+                }
+            }
+
+            write!(output, "{}", v).unwrap();
+            line_len += v_len;
+        });
+
+        // every line (and hence the file) ends with a newline character
+        if line_len > 0 {
+            output.push('\n');
         }
     });
 
-    // to ensure that file ends with a newline character
-    output.push("".to_string());
-
-    output.join("\n")
+    output
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::graphics::Color;
+    use crate::math::{assert_float_eq, assert_float_eq_within};
 
     use super::*;
 
@@ -107,6 +300,229 @@ mod tests {
         );
     }
 
+    /// Straightforward version of `ppm_from_rgb8()`, building a string per value
+    fn reference_ppm(canvas: &Canvas, max_line_len: usize) -> String {
+        let mut output = vec![
+            "P3".to_string(),
+            format!("{} {}", canvas.w(), canvas.h()),
+            "255".to_string(),
+        ];
+
+        (0..canvas.h()).for_each(|y| {
+            let mut current = String::new();
+            (0..canvas.w())
+                .flat_map(|x| canvas.px(x, y).to_rgb8())
+                .for_each(|v| {
+                    if current.is_empty() {
+                        current += &v.to_string();
+                    } else {
+                        let v_str = format!(" {}", v);
+                        if current.len() + v_str.len() <= max_line_len {
+                            current += &v_str;
+                        } else {
+                            output.push(current.to_string());
+                            current = v.to_string();
+                        }
+                    }
+                });
+            if !current.is_empty() {
+                output.push(current);
+            }
+        });
+        output.push("".to_string());
+
+        output.join("\n")
+    }
+
+    #[test]
+    fn test_ppm_matches_reference() {
+        let c = Canvas::from_fn(23, 7, |x, y| {
+            Color::new(
+                ((x * 37 + y * 11) % 256) as f64 / 255.0,
+                ((x * y) % 13) as f64 / 12.0,
+                if (x + y) % 3 == 0 { 1.0 } else { 0.0 },
+            )
+        });
+
+        assert_eq!(canvas_to_ppm(&c), reference_ppm(&c, 70));
+        [1, 3, 4, 20, 71].into_iter().for_each(|max_line_len| {
+            let options = PpmOptions {
+                max_line_len: Some(max_line_len),
+            };
+            assert_eq!(
+                canvas_to_ppm_with_options(&c, options),
+                reference_ppm(&c, max_line_len)
+            );
+        });
+        assert_eq!(
+            canvas_to_ppm(&Canvas::new(0, 3)),
+            reference_ppm(&Canvas::new(0, 3), 70)
+        );
+    }
+
+    #[test]
+    fn test_ppm_gamma() {
+        let mut c = Canvas::new(5, 3);
+        c.write_px(0, 0, Color::new(1.5, 0.5, 0.0));
+        c.write_px(2, 1, Color::new(0.0, 0.5, 0.0));
+        c.write_px(4, 2, Color::new(-0.5, 0.0, 1.0));
+
+        assert_eq!(canvas_to_ppm_gamma(&c, 1.0), canvas_to_ppm(&c));
+
+        let ppm = canvas_to_ppm_gamma(&c, 2.2);
+        let ppm = ppm.lines().collect::<Vec<_>>();
+        assert_eq!(ppm[3], "255 186 0 0 0 0 0 0 0 0 0 0 0 0 0");
+        assert_eq!(ppm[4], "0 0 0 0 0 0 0 186 0 0 0 0 0 0 0");
+        assert_eq!(ppm[5], "0 0 0 0 0 0 0 0 0 0 0 0 0 0 255");
+    }
+
+    #[test]
+    fn test_ppm_with_options() {
+        let mut c = Canvas::new(10, 2);
+        (0..2).for_each(|y| {
+            (0..10).for_each(|x| {
+                c.write_px(x, y, Color::new(1.0, 0.8, 0.6));
+            })
+        });
+
+        assert_eq!(
+            canvas_to_ppm_with_options(&c, PpmOptions::default()),
+            canvas_to_ppm(&c)
+        );
+
+        let ppm = canvas_to_ppm_with_options(&c, PpmOptions { max_line_len: None });
+        let ppm = ppm.lines().collect::<Vec<_>>();
+        assert_eq!(ppm.len(), 5);
+        assert_eq!(ppm[3], ["255 204 153"; 10].join(" "));
+        assert_eq!(ppm[4], ppm[3]);
+
+        let ppm = canvas_to_ppm_with_options(
+            &c,
+            PpmOptions {
+                max_line_len: Some(20),
+            },
+        );
+        let ppm = ppm.lines().collect::<Vec<_>>();
+        // "255 204 153 255 204" is 19 characters, adding " 153" would make it 23
+        assert_eq!(ppm[3], "255 204 153 255 204");
+        assert_eq!(ppm[4], "153 255 204 153 255");
+        assert!(ppm[3..].iter().all(|line| line.len() <= 20));
+    }
+
+    fn mid_gray_canvas() -> Canvas {
+        let mut c = Canvas::new(8, 8);
+        (0..8).for_each(|y| {
+            (0..8).for_each(|x| c.write_px(x, y, Color::new(0.5, 0.5, 0.5)));
+        });
+        c
+    }
+
+    fn ppm_values(ppm: &str) -> Vec<u8> {
+        ppm.lines()
+            .skip(3)
+            .flat_map(|line| line.split(' '))
+            .map(|v| v.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_ppm_dithered_none() {
+        let mut c = mid_gray_canvas();
+        c.write_px(1, 2, Color::new(1.5, 0.2, -0.5));
+
+        assert_eq!(
+            canvas_to_ppm_dithered(&c, DitherMode::None),
+            canvas_to_ppm(&c)
+        );
+    }
+
+    #[test]
+    fn test_ppm_dithered_mid_gray() {
+        let c = mid_gray_canvas();
+
+        [DitherMode::Ordered, DitherMode::FloydSteinberg]
+            .into_iter()
+            .for_each(|mode| {
+                let values = ppm_values(&canvas_to_ppm_dithered(&c, mode));
+                assert_eq!(values.len(), 8 * 8 * 3);
+                assert!(values.iter().all(|&v| v == 127 || v == 128));
+                assert!(values.contains(&127));
+                assert!(values.contains(&128));
+            });
+    }
+
+    #[test]
+    fn test_ppm_dithered_extremes() {
+        let mut c = Canvas::new(4, 4);
+        c.write_px(0, 0, Color::new(1.0, 1.0, 1.0));
+
+        [DitherMode::Ordered, DitherMode::FloydSteinberg]
+            .into_iter()
+            .for_each(|mode| {
+                let values = ppm_values(&canvas_to_ppm_dithered(&c, mode));
+                assert_eq!(values[0..3], [255, 255, 255]);
+                assert!(values[3..].iter().all(|&v| v == 0));
+            });
+    }
+
+    #[test]
+    fn test_canvas_from_ppm() {
+        let ppm = "P3\n# a comment\n2 1 # width and height\n10\n10 5 0\n0 0 10\n";
+        let c = canvas_from_ppm(ppm).unwrap();
+        assert_eq!((c.w(), c.h()), (2, 1));
+        assert_float_eq(c.px(0, 0), Color::new(1.0, 0.5, 0.0));
+        assert_float_eq(c.px(1, 0), Color::new(0.0, 0.0, 1.0));
+
+        let c = Canvas::from_fn(7, 4, |x, y| Color::new(x as f64 / 6.0, y as f64 / 3.0, 0.3));
+        assert_eq!(
+            canvas_from_ppm(&canvas_to_ppm(&c)).unwrap().to_rgb8(),
+            c.to_rgb8()
+        );
+
+        [
+            ("P6\n1 1\n255\n0 0 0\n", PpmError::NotPlainPpm),
+            ("P3\n1 x\n255\n", PpmError::InvalidNumber("x".to_string())),
+            ("P3\n1 1\n0\n0 0 0\n", PpmError::ZeroMaxValue),
+            ("P3\n2 1\n255\n0 0 0\n", PpmError::UnexpectedEnd),
+            ("P3\n1 1\n255\n0 0 0 0\n", PpmError::TrailingData),
+            (
+                "P3 0 18446744073709551615 255",
+                PpmError::Canvas(CanvasError::TooLarge {
+                    w: 0,
+                    h: usize::MAX,
+                }),
+            ),
+        ]
+        .into_iter()
+        .for_each(|(ppm, err)| assert_eq!(canvas_from_ppm(ppm).unwrap_err(), err));
+    }
+
+    #[test]
+    fn test_write_image() {
+        let dir = std::env::temp_dir().join(format!("rtc_write_image_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let c = Canvas::from_fn(3, 2, |x, y| Color::new(x as f64 / 2.0, y as f64, 0.5));
+
+        ["image.ppm", "IMAGE.PPM"].into_iter().for_each(|name| {
+            write_image(&c, &dir.join(name)).unwrap();
+            let read_back = canvas_from_ppm(&fs::read_to_string(dir.join(name)).unwrap());
+            assert_float_eq_within(read_back.unwrap(), c.clone(), 0.5 / 255.0);
+        });
+
+        let err = write_image(&c, &dir.join("image.png")).unwrap_err();
+        assert!(err.to_string().contains("`png` feature"));
+
+        ["image.png", "image.txt", "image"]
+            .into_iter()
+            .for_each(|name| {
+                let err = write_image(&c, &dir.join(name)).unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+                assert!(!dir.join(name).exists());
+            });
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_ppm_end_newline() {
         let ppm = canvas_to_ppm(&Canvas::new(5, 3));
@@ -1,3 +1,4 @@
+pub mod animation;
 pub mod geometry;
 pub mod graphics;
 pub mod image;
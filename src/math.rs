@@ -1,12 +1,22 @@
-use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::{
+    fmt,
+    ops::{Add, Div, Index, IndexMut, Mul, Neg, Sub},
+};
 
 pub trait FloatEq {
     fn float_eq(&self, other: &Self) -> bool;
+
+    /// Whether every component differs by at most `eps`
+    fn float_eq_within(&self, other: &Self, eps: f64) -> bool;
 }
 
 impl FloatEq for f64 {
     fn float_eq(&self, other: &Self) -> bool {
-        (self - other).abs() <= std::f64::EPSILON
+        self.float_eq_within(other, f64::EPSILON)
+    }
+
+    fn float_eq_within(&self, other: &Self, eps: f64) -> bool {
+        (self - other).abs() <= eps
     }
 }
 
@@ -22,6 +32,31 @@ where
     );
 }
 
+/// Same as `assert_float_eq()`, but with an explicit tolerance
+pub fn assert_float_eq_within<T>(left: T, right: T, eps: f64)
+where
+    T: FloatEq + std::fmt::Debug,
+{
+    assert_float_eq_within_msg(left, right, eps, "");
+}
+
+/// Same as `assert_float_eq_within()`, but adds `msg` to the panic message to tell
+/// apart many similar comparisons
+pub fn assert_float_eq_within_msg<T>(left: T, right: T, eps: f64, msg: &str)
+where
+    T: FloatEq + std::fmt::Debug,
+{
+    assert!(
+        left.float_eq_within(&right, eps),
+        "left = {:?}, right = {:?}, eps = {}{}{}",
+        left,
+        right,
+        eps,
+        if msg.is_empty() { "" } else { ": " },
+        msg
+    );
+}
+
 pub fn assert_float_ne<T>(left: T, right: T)
 where
     T: FloatEq + std::fmt::Debug,
@@ -39,6 +74,9 @@ pub struct Vector4f {
     vals: [f64; 4],
 }
 
+/// The book's name for a 4-component tuple
+pub type Tuple4f = Vector4f;
+
 impl Vector4f {
     pub fn new(x: f64, y: f64, z: f64, w: f64) -> Self {
         Self { vals: [x, y, z, w] }
@@ -64,6 +102,22 @@ impl Vector4f {
         self.vals[3].float_eq(&1.0)
     }
 
+    pub fn new_point(x: f64, y: f64, z: f64) -> Self {
+        Self::new_point3_tuple(x, y, z)
+    }
+
+    pub fn new_vector(x: f64, y: f64, z: f64) -> Self {
+        Self::new_vector3_tuple(x, y, z)
+    }
+
+    pub fn is_point(&self) -> bool {
+        self.is_point3_tuple()
+    }
+
+    pub fn is_vector(&self) -> bool {
+        self.is_vector3_tuple()
+    }
+
     pub fn magnitude(&self) -> f64 {
         self.vals.iter().map(|a| a * a).sum::<f64>().sqrt()
     }
@@ -80,6 +134,20 @@ impl Vector4f {
             .sum()
     }
 
+    /// Cross product of the x, y and z components, w is ignored (and 0 in the result)
+    pub fn cross(&self, other: &Self) -> Self {
+        Self::new_vector3_tuple(
+            self.vals[1] * other.vals[2] - self.vals[2] * other.vals[1],
+            self.vals[2] * other.vals[0] - self.vals[0] * other.vals[2],
+            self.vals[0] * other.vals[1] - self.vals[1] * other.vals[0],
+        )
+    }
+
+    /// Hadamard product / Schur product (multiply each component by other)
+    pub fn hadamard(&self, other: &Self) -> Self {
+        self.binary_op(other, |a, b| a * b)
+    }
+
     pub fn x(&self) -> f64 {
         self.vals[0]
     }
@@ -141,10 +209,30 @@ impl Vector4f {
 
 impl FloatEq for Vector4f {
     fn float_eq(&self, other: &Self) -> bool {
+        self.float_eq_within(other, f64::EPSILON)
+    }
+
+    fn float_eq_within(&self, other: &Self, eps: f64) -> bool {
         self.vals
             .iter()
             .zip(other.vals.iter())
-            .all(|(a, b)| a.float_eq(b))
+            .all(|(a, b)| a.float_eq_within(b, eps))
+    }
+}
+
+impl Index<usize> for Vector4f {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        assert!(index < 4, "Index {} is out of range for Vector4f", index);
+        &self.vals[index]
+    }
+}
+
+impl IndexMut<usize> for Vector4f {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        assert!(index < 4, "Index {} is out of range for Vector4f", index);
+        &mut self.vals[index]
     }
 }
 
@@ -219,6 +307,64 @@ impl Point3f {
     pub fn z(&self) -> f64 {
         self.0.vals[2]
     }
+
+    /// Linear interpolation, t = 0 gives self, t = 1 gives other
+    pub fn lerp(&self, other: &Point3f, t: f64) -> Point3f {
+        *self + (*other - *self) * t
+    }
+
+    pub fn translated(self, values: Vector3f) -> Point3f {
+        Matrix4x4f::translation(values) * self
+    }
+
+    pub fn scaled(self, values: Vector3f) -> Point3f {
+        Matrix4x4f::scaling(values) * self
+    }
+
+    pub fn rotated_x(self, rad: f64) -> Point3f {
+        Matrix4x4f::rotation_x(rad) * self
+    }
+
+    pub fn rotated_y(self, rad: f64) -> Point3f {
+        Matrix4x4f::rotation_y(rad) * self
+    }
+
+    pub fn rotated_z(self, rad: f64) -> Point3f {
+        Matrix4x4f::rotation_z(rad) * self
+    }
+
+    /// Smallest of each component, e.g. the min corner of a box containing both points
+    pub fn component_min(&self, other: &Point3f) -> Point3f {
+        Point3f::new(
+            self.x().min(other.x()),
+            self.y().min(other.y()),
+            self.z().min(other.z()),
+        )
+    }
+
+    /// Largest of each component, e.g. the max corner of a box containing both points
+    pub fn component_max(&self, other: &Point3f) -> Point3f {
+        Point3f::new(
+            self.x().max(other.x()),
+            self.y().max(other.y()),
+            self.z().max(other.z()),
+        )
+    }
+}
+
+impl Index<usize> for Point3f {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        assert!(index < 3, "Index {} is out of range for Point3f", index);
+        &self.0.vals[index]
+    }
+}
+
+impl fmt::Display for Point3f {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.x(), self.y(), self.z())
+    }
 }
 
 impl From<Vector4f> for Point3f {
@@ -228,6 +374,12 @@ impl From<Vector4f> for Point3f {
     }
 }
 
+impl From<(f64, f64, f64)> for Point3f {
+    fn from((x, y, z): (f64, f64, f64)) -> Self {
+        Point3f::new(x, y, z)
+    }
+}
+
 impl Add<Vector3f> for Point3f {
     type Output = Point3f;
 
@@ -254,7 +406,11 @@ impl Sub<Vector3f> for Point3f {
 
 impl FloatEq for Point3f {
     fn float_eq(&self, other: &Self) -> bool {
-        self.0.float_eq(&other.0)
+        self.float_eq_within(other, f64::EPSILON)
+    }
+
+    fn float_eq_within(&self, other: &Self, eps: f64) -> bool {
+        self.0.float_eq_within(&other.0, eps)
     }
 }
 
@@ -270,8 +426,20 @@ impl Vector3f {
         self.0.magnitude()
     }
 
+    /// Panics if the vector is a zero vector, use `try_normalize()` if the vector can be zero.
     pub fn normalize(&self) -> Self {
-        self.0.normalize().into()
+        self.try_normalize()
+            .expect("Cannot normalize a zero vector")
+    }
+
+    /// `None` for a zero vector, or if its magnitude is not finite
+    pub fn try_normalize(&self) -> Option<Self> {
+        let magnitude = self.magnitude();
+        if magnitude == 0.0 || !magnitude.is_finite() {
+            None
+        } else {
+            Some(self.0.normalize().into())
+        }
     }
 
     pub fn dot(&self, other: &Self) -> f64 {
@@ -279,11 +447,7 @@ impl Vector3f {
     }
 
     pub fn cross(&self, other: &Self) -> Self {
-        Self::new(
-            self.0.vals[1] * other.0.vals[2] - self.0.vals[2] * other.0.vals[1],
-            self.0.vals[2] * other.0.vals[0] - self.0.vals[0] * other.0.vals[2],
-            self.0.vals[0] * other.0.vals[1] - self.0.vals[1] * other.0.vals[0],
-        )
+        Vector3f(self.0.cross(&other.0))
     }
 
     pub fn x(&self) -> f64 {
@@ -301,6 +465,97 @@ impl Vector3f {
     pub fn reflect(&self, normal: &Vector3f) -> Vector3f {
         *self - *normal * 2.0 * self.dot(normal)
     }
+
+    /// Direction after passing through a surface with the given normal (Snell's law),
+    /// where `n_ratio` is n1 / n2. Both vectors are expected to be normalized, with the
+    /// normal pointing against this vector. Returns `None` on total internal reflection.
+    pub fn refract(&self, normal: &Vector3f, n_ratio: f64) -> Option<Vector3f> {
+        let cos_i = -self.dot(normal);
+        let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
+
+        if sin2_t > 1.0 {
+            None
+        } else {
+            let cos_t = (1.0 - sin2_t).sqrt();
+            Some(*self * n_ratio + *normal * (n_ratio * cos_i - cos_t))
+        }
+    }
+
+    /// In radians, from 0 to PI
+    pub fn angle_between(&self, other: &Vector3f) -> f64 {
+        // rounding errors can push the cosine slightly outside of [-1, 1], which acos()
+        // would turn into NaN
+        (self.dot(other) / (self.magnitude() * other.magnitude()))
+            .clamp(-1.0, 1.0)
+            .acos()
+    }
+
+    pub fn project_onto(&self, onto: &Vector3f) -> Vector3f {
+        *onto * (self.dot(onto) / onto.dot(onto))
+    }
+
+    /// Linear interpolation, t = 0 gives self, t = 1 gives other
+    pub fn lerp(&self, other: &Vector3f, t: f64) -> Vector3f {
+        *self + (*other - *self) * t
+    }
+
+    // there is no `translated()`, as translation does not affect vectors
+
+    pub fn scaled(self, values: Vector3f) -> Vector3f {
+        Matrix4x4f::scaling(values) * self
+    }
+
+    pub fn rotated_x(self, rad: f64) -> Vector3f {
+        Matrix4x4f::rotation_x(rad) * self
+    }
+
+    pub fn rotated_y(self, rad: f64) -> Vector3f {
+        Matrix4x4f::rotation_y(rad) * self
+    }
+
+    pub fn rotated_z(self, rad: f64) -> Vector3f {
+        Matrix4x4f::rotation_z(rad) * self
+    }
+
+    pub fn component_min(&self, other: &Vector3f) -> Vector3f {
+        Vector3f::new(
+            self.x().min(other.x()),
+            self.y().min(other.y()),
+            self.z().min(other.z()),
+        )
+    }
+
+    pub fn component_max(&self, other: &Vector3f) -> Vector3f {
+        Vector3f::new(
+            self.x().max(other.x()),
+            self.y().max(other.y()),
+            self.z().max(other.z()),
+        )
+    }
+
+    pub fn abs(&self) -> Vector3f {
+        Vector3f::new(self.x().abs(), self.y().abs(), self.z().abs())
+    }
+
+    /// Largest absolute value of the components
+    pub fn max_component(&self) -> f64 {
+        self.x().abs().max(self.y().abs()).max(self.z().abs())
+    }
+}
+
+impl Index<usize> for Vector3f {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        assert!(index < 3, "Index {} is out of range for Vector3f", index);
+        &self.0.vals[index]
+    }
+}
+
+impl fmt::Display for Vector3f {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}, {}, {}>", self.x(), self.y(), self.z())
+    }
 }
 
 impl From<Vector4f> for Vector3f {
@@ -310,6 +565,12 @@ impl From<Vector4f> for Vector3f {
     }
 }
 
+impl From<(f64, f64, f64)> for Vector3f {
+    fn from((x, y, z): (f64, f64, f64)) -> Self {
+        Vector3f::new(x, y, z)
+    }
+}
+
 impl Add<Vector3f> for Vector3f {
     type Output = Vector3f;
 
@@ -360,7 +621,11 @@ impl Div<f64> for Vector3f {
 
 impl FloatEq for Vector3f {
     fn float_eq(&self, other: &Self) -> bool {
-        self.0.float_eq(&other.0)
+        self.float_eq_within(other, f64::EPSILON)
+    }
+
+    fn float_eq_within(&self, other: &Self, eps: f64) -> bool {
+        self.0.float_eq_within(&other.0, eps)
     }
 }
 
@@ -438,6 +703,30 @@ impl<const N: usize, const O: usize> BaseMatrix<N, O> {
         }
     }
 
+    /// Same as `float_eq()` with `identity()`
+    pub fn is_identity(&self) -> bool {
+        self.float_eq(&Self::identity())
+    }
+
+    /// Same as `float_eq_within()`
+    pub fn approx_equals(&self, other: &Self, eps: f64) -> bool {
+        self.float_eq_within(other, eps)
+    }
+
+    /// Component-wise linear interpolation, t = 0 gives self, t = 1 gives other
+    pub fn lerp(&self, other: &Self, t: f64) -> Self {
+        *self + (*other - *self) * t
+    }
+
+    fn binary_op<F>(&self, other: &Self, op: F) -> Self
+    where
+        F: Fn(&f64, &f64) -> f64,
+    {
+        Self {
+            vals: std::array::from_fn(|i| op(&self.vals[i], &other.vals[i])),
+        }
+    }
+
     fn submatrix_vals(&self, remove_r: usize, remove_c: usize) -> Vec<f64> {
         Self::assert_bounds(remove_r, remove_c);
 
@@ -486,10 +775,80 @@ where
 
 impl<const N: usize, const O: usize> FloatEq for BaseMatrix<N, O> {
     fn float_eq(&self, other: &Self) -> bool {
+        self.float_eq_within(other, f64::EPSILON)
+    }
+
+    fn float_eq_within(&self, other: &Self, eps: f64) -> bool {
         self.vals
             .iter()
             .zip(other.vals.iter())
-            .all(|(a, b)| a.float_eq(b))
+            .all(|(a, b)| a.float_eq_within(b, eps))
+    }
+}
+
+/// Row-major values, same as `new()`
+impl<const N: usize, const O: usize> From<[f64; N]> for BaseMatrix<N, O> {
+    fn from(vals: [f64; N]) -> Self {
+        Self::new(vals)
+    }
+}
+
+/// Row-major values, same as `new()`
+impl<const N: usize, const O: usize> From<BaseMatrix<N, O>> for [f64; N] {
+    fn from(matrix: BaseMatrix<N, O>) -> Self {
+        matrix.vals
+    }
+}
+
+impl<const N: usize, const O: usize> fmt::Display for BaseMatrix<N, O> {
+    /// One row per line, with each column right-aligned
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cells = self.vals.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        let widths = (0..Self::MAT_ORDER)
+            .map(|c| {
+                (0..Self::MAT_ORDER)
+                    .map(|r| cells[r * Self::MAT_ORDER + c].len())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+
+        let rows = (0..Self::MAT_ORDER)
+            .map(|r| {
+                (0..Self::MAT_ORDER)
+                    .map(|c| format!("{:>1$}", cells[r * Self::MAT_ORDER + c], widths[c]))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", rows.join("\n"))
+    }
+}
+
+impl<const N: usize, const O: usize> Add for BaseMatrix<N, O> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.binary_op(&rhs, |a, b| a + b)
+    }
+}
+
+impl<const N: usize, const O: usize> Sub for BaseMatrix<N, O> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.binary_op(&rhs, |a, b| a - b)
+    }
+}
+
+impl<const N: usize, const O: usize> Mul<f64> for BaseMatrix<N, O> {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        Self {
+            vals: self.vals.map(|a| a * rhs),
+        }
     }
 }
 
@@ -527,6 +886,18 @@ where
 pub type Matrix4x4f = BaseMatrix<16, 4>;
 
 impl Matrix4x4f {
+    pub fn from_rows(rows: [[f64; 4]; 4]) -> Self {
+        Self {
+            vals: std::array::from_fn(|i| rows[i / 4][i % 4]),
+        }
+    }
+
+    pub fn from_columns(columns: [[f64; 4]; 4]) -> Self {
+        Self {
+            vals: std::array::from_fn(|i| columns[i % 4][i / 4]),
+        }
+    }
+
     pub fn translation(values: Vector3f) -> Self {
         Self {
             vals: [
@@ -650,6 +1021,58 @@ impl Matrix4x4f {
         }
     }
 
+    /// OpenGL-style orthographic projection, looking down the -z axis. Maps the given box
+    /// to normalized device coordinates (-1 to 1 on all axes).
+    pub fn orthographic(left: f64, right: f64, bottom: f64, top: f64, near: f64, far: f64) -> Self {
+        Self {
+            vals: [
+                2.0 / (right - left),
+                0.0,
+                0.0,
+                -(right + left) / (right - left),
+                0.0,
+                2.0 / (top - bottom),
+                0.0,
+                -(top + bottom) / (top - bottom),
+                0.0,
+                0.0,
+                -2.0 / (far - near),
+                -(far + near) / (far - near),
+                0.0,
+                0.0,
+                0.0,
+                1.0,
+            ],
+        }
+    }
+
+    /// OpenGL-style perspective projection, looking down the -z axis. The resulting w
+    /// is the depth of the point, divide by it to get normalized device coordinates.
+    pub fn perspective(fov_y: f64, aspect: f64, near: f64, far: f64) -> Self {
+        let f = 1.0 / (fov_y / 2.0).tan();
+
+        Self {
+            vals: [
+                f / aspect,
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                f,
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                (far + near) / (near - far),
+                2.0 * far * near / (near - far),
+                0.0,
+                0.0,
+                -1.0,
+                0.0,
+            ],
+        }
+    }
+
     pub fn translate(&self, values: Vector3f) -> Self {
         Matrix4x4f::translation(values) * *self
     }
@@ -673,6 +1096,82 @@ impl Matrix4x4f {
     pub fn shear(&self, x_y: f64, x_z: f64, y_x: f64, y_z: f64, z_x: f64, z_y: f64) -> Self {
         Matrix4x4f::shearing(x_y, x_z, y_x, y_z, z_x, z_y) * *self
     }
+
+    /// Multiplies the matrix with itself n times, negative n uses the inverse instead.
+    /// Panics if n is negative and the matrix is not invertible.
+    pub fn powi(&self, n: i32) -> Self {
+        let mut base = if n < 0 {
+            self.inverse()
+                .expect("Matrix must be invertible for a negative exponent")
+        } else {
+            *self
+        };
+        let mut exp = n.unsigned_abs();
+        let mut result = Self::identity();
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exp >>= 1;
+        }
+        result
+    }
+}
+
+/// Records transformations and combines them with `build()`, so that they are applied
+/// to a point in the order they were listed.
+///
+/// Listing `a`, then `b`, then `c` builds the product `c * b * a`: the first operation
+/// ends up right next to the point being transformed. This is the same order as the
+/// fluent methods on `Matrix4x4f` (e.g. `translate()`), but without needing to start
+/// from an existing matrix.
+#[derive(Debug, Clone, Default)]
+pub struct TransformBuilder {
+    ops: Vec<Matrix4x4f>,
+}
+
+impl TransformBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an arbitrary transformation matrix
+    pub fn then(mut self, matrix: Matrix4x4f) -> Self {
+        self.ops.push(matrix);
+        self
+    }
+
+    pub fn translate(self, values: Vector3f) -> Self {
+        self.then(Matrix4x4f::translation(values))
+    }
+
+    pub fn scale(self, values: Vector3f) -> Self {
+        self.then(Matrix4x4f::scaling(values))
+    }
+
+    pub fn rotate_x(self, rad: f64) -> Self {
+        self.then(Matrix4x4f::rotation_x(rad))
+    }
+
+    pub fn rotate_y(self, rad: f64) -> Self {
+        self.then(Matrix4x4f::rotation_y(rad))
+    }
+
+    pub fn rotate_z(self, rad: f64) -> Self {
+        self.then(Matrix4x4f::rotation_z(rad))
+    }
+
+    pub fn shear(self, x_y: f64, x_z: f64, y_x: f64, y_z: f64, z_x: f64, z_y: f64) -> Self {
+        self.then(Matrix4x4f::shearing(x_y, x_z, y_x, y_z, z_x, z_y))
+    }
+
+    pub fn build(&self) -> Matrix4x4f {
+        self.ops
+            .iter()
+            .fold(Matrix4x4f::identity(), |acc, op| *op * acc)
+    }
 }
 
 impl Submatrix for Matrix4x4f {
@@ -690,7 +1189,7 @@ impl Mul<Vector4f> for Matrix4x4f {
 
     fn mul(self, rhs: Vector4f) -> Self::Output {
         let vals = (0..4)
-            .map(|r| (0..4).map(|i| self.get(r, i) * rhs.vals[i]).sum())
+            .map(|r| (0..4).map(|i| self.get(r, i) * rhs[i]).sum())
             .collect::<Vec<_>>();
 
         Vector4f {
@@ -710,8 +1209,28 @@ impl Mul<Point3f> for Matrix4x4f {
 impl Mul<Vector3f> for Matrix4x4f {
     type Output = Vector3f;
 
+    /// Only the upper-left 3x3 part of the matrix is used, any contribution to w is
+    /// discarded (e.g. when multiplying with a transposed inverse, see page 82)
     fn mul(self, rhs: Vector3f) -> Self::Output {
-        (self * rhs.0).into()
+        let vals = (0..3)
+            .map(|r| (0..3).map(|i| self.get(r, i) * rhs[i]).sum())
+            .collect::<Vec<f64>>();
+
+        Vector3f::new(vals[0], vals[1], vals[2])
+    }
+}
+
+/// Array of rows, same as `Matrix4x4f::from_rows()`
+impl From<[[f64; 4]; 4]> for Matrix4x4f {
+    fn from(rows: [[f64; 4]; 4]) -> Self {
+        Self::from_rows(rows)
+    }
+}
+
+/// Array of rows
+impl From<Matrix4x4f> for [[f64; 4]; 4] {
+    fn from(matrix: Matrix4x4f) -> Self {
+        std::array::from_fn(|r| std::array::from_fn(|c| matrix.get(r, c)))
     }
 }
 
@@ -737,6 +1256,50 @@ impl Submatrix for Matrix2x2f {
     }
 }
 
+/// Reproducible stream of numbers in `0..1`, used for jittering samples (e.g. soft
+/// shadows, anti-aliasing) without making renders differ between runs.
+#[derive(Debug, Clone)]
+pub enum Sequence {
+    /// Cycles through the given values, mostly useful for tests
+    Cyclic { vals: Vec<f64>, next: usize },
+    /// xorshift64 pseudo-random generator
+    XorShift { state: u64 },
+}
+
+impl Sequence {
+    pub fn cyclic(vals: Vec<f64>) -> Self {
+        assert!(!vals.is_empty(), "Cyclic sequence needs at least one value");
+        Sequence::Cyclic { vals, next: 0 }
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        // xorshift gets stuck at 0, so replace it with an arbitrary non-zero state
+        let state = if seed == 0 {
+            0x9e37_79b9_7f4a_7c15
+        } else {
+            seed
+        };
+        Sequence::XorShift { state }
+    }
+
+    pub fn next_f64(&mut self) -> f64 {
+        match self {
+            Sequence::Cyclic { vals, next } => {
+                let val = vals[*next];
+                *next = (*next + 1) % vals.len();
+                val
+            }
+            Sequence::XorShift { state } => {
+                *state ^= *state << 13;
+                *state ^= *state >> 7;
+                *state ^= *state << 17;
+                // top 53 bits fit exactly in the mantissa, giving a value in 0..1
+                (*state >> 11) as f64 / (1u64 << 53) as f64
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -747,6 +1310,38 @@ mod tests {
         assert!((0.1 + 0.2).float_eq(&0.3));
     }
 
+    #[test]
+    fn test_float_eq_within() {
+        assert!(1.0.float_eq_within(&1.05, 0.1));
+        assert!(!1.0.float_eq_within(&1.2, 0.1));
+        assert!(Vector3f::new(1.0, 2.0, 3.0).float_eq_within(&Vector3f::new(1.01, 2.0, 2.99), 0.02));
+
+        assert_float_eq_within(1.0, 1.05, 0.1);
+        assert_float_eq_within(
+            Matrix4x4f::identity(),
+            Matrix4x4f::scaling(Vector3f::new(1.0001, 1.0, 1.0)),
+            0.001,
+        );
+        assert_float_eq_within_msg(
+            Point3f::new(0.0, 0.0, 0.0),
+            Point3f::new(0.0, 1e-5, 0.0),
+            1e-4,
+            "origin",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "eps = 0.1")]
+    fn test_assert_float_eq_within_fail() {
+        assert_float_eq_within(1.0, 1.2, 0.1);
+    }
+
+    #[test]
+    #[should_panic(expected = "left = 1.0, right = 1.2, eps = 0.1: cell (2, 3)")]
+    fn test_assert_float_eq_within_msg_fail() {
+        assert_float_eq_within_msg(1.0, 1.2, 0.1, "cell (2, 3)");
+    }
+
     #[test]
     fn test_vector4f_new() {
         assert_eq!(
@@ -764,6 +1359,33 @@ mod tests {
         assert!(!v.is_vector3_tuple());
     }
 
+    #[test]
+    fn test_tuple4f_is_point_and_vector() {
+        let p = Tuple4f::new(4.3, -4.2, 3.1, 1.0);
+        assert!(p.is_point());
+        assert!(!p.is_vector());
+
+        let v = Tuple4f::new(4.3, -4.2, 3.1, 0.0);
+        assert!(!v.is_point());
+        assert!(v.is_vector());
+    }
+
+    #[test]
+    fn test_tuple4f_new_point_and_vector() {
+        assert_eq!(
+            Tuple4f::new_point(4.0, -4.0, 3.0),
+            Vector4f {
+                vals: [4.0, -4.0, 3.0, 1.0],
+            }
+        );
+        assert_eq!(
+            Tuple4f::new_vector(4.0, -4.0, 3.0),
+            Vector4f {
+                vals: [4.0, -4.0, 3.0, 0.0],
+            }
+        );
+    }
+
     #[test]
     fn test_point3f_from() {
         let v = Vector4f::new(4.3, -4.2, 3.1, 1.0);
@@ -994,6 +1616,33 @@ mod tests {
         assert_float_eq(Vector3f::new(1.0, 2.0, 3.0).normalize().magnitude(), 1.0);
     }
 
+    #[test]
+    #[should_panic(expected = "Cannot normalize a zero vector")]
+    fn test_vec_normalize_zero() {
+        Vector3f::new(0.0, 0.0, 0.0).normalize();
+    }
+
+    #[test]
+    fn test_vec_try_normalize() {
+        assert_eq!(Vector3f::new(0.0, 0.0, 0.0).try_normalize(), None);
+
+        assert_float_eq(
+            Vector3f::new(4.0, 0.0, 0.0).try_normalize().unwrap(),
+            Vector3f::new(1.0, 0.0, 0.0),
+        );
+
+        let tiny = Vector3f::new(1e-9, 2e-9, 0.0).try_normalize().unwrap();
+        assert_float_eq(tiny.magnitude(), 1.0);
+
+        // smaller than EPSILON, but still not a zero vector
+        assert_float_eq(
+            Vector3f::new(1e-17, 0.0, 0.0).normalize(),
+            Vector3f::new(1.0, 0.0, 0.0),
+        );
+        assert_eq!(Vector3f::new(f64::NAN, 0.0, 0.0).try_normalize(), None);
+        assert_eq!(Vector3f::new(f64::INFINITY, 0.0, 0.0).try_normalize(), None);
+    }
+
     #[test]
     fn test_vec_dot() {
         assert_float_eq(
@@ -1017,6 +1666,97 @@ mod tests {
         assert_float_eq(b.cross(&a), Vector3f::new(16.0, -9.0, -1.0));
     }
 
+    #[test]
+    fn test_vector4f_cross() {
+        let a = Vector4f::new(1.0, 2.0, 3.0, 0.0);
+        let b = Vector4f::new(2.0, 3.0, 4.0, 0.0);
+        assert_eq!(a.cross(&b), Vector4f::new(-1.0, 2.0, -1.0, 0.0));
+        // w is ignored
+        assert_eq!(
+            Vector4f::new(1.0, 2.0, 3.0, 7.0).cross(&b),
+            Vector4f::new(-1.0, 2.0, -1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_vector4f_hadamard() {
+        let a = Vector4f::new(1.0, 2.0, 3.0, 4.0);
+        let b = Vector4f::new(2.0, -3.0, 0.5, 0.0);
+        assert_eq!(a.hadamard(&b), Vector4f::new(2.0, -6.0, 1.5, 0.0));
+        assert_eq!(a.hadamard(&b), b.hadamard(&a));
+    }
+
+    #[test]
+    fn test_vector3f_refract() {
+        let normal = Vector3f::new(0.0, 0.0, -1.0);
+
+        assert_float_eq(
+            Vector3f::new(0.0, 0.0, 1.0).refract(&normal, 1.5).unwrap(),
+            Vector3f::new(0.0, 0.0, 1.0),
+        );
+
+        let incident_angle = 30_f64.to_radians();
+        let incident = Vector3f::new(incident_angle.sin(), 0.0, incident_angle.cos());
+        let refracted = incident.refract(&normal, 1.5).unwrap();
+        assert_float_eq(refracted.magnitude(), 1.0);
+        assert_float_eq(refracted.angle_between(&-normal), 0.75_f64.asin());
+        assert!(refracted.x() > 0.0);
+
+        let incident_angle = 60_f64.to_radians();
+        let incident = Vector3f::new(incident_angle.sin(), 0.0, incident_angle.cos());
+        assert_eq!(incident.refract(&normal, 1.5), None);
+        assert!(incident.refract(&normal, 1.0 / 1.5).is_some());
+    }
+
+    #[test]
+    fn test_vector3f_angle_between() {
+        use std::f64::consts::PI;
+
+        assert_float_eq(
+            Vector3f::new(1.0, 0.0, 0.0).angle_between(&Vector3f::new(0.0, 3.0, 0.0)),
+            PI / 2.0,
+        );
+        assert_float_eq(
+            Vector3f::new(1.0, 2.0, 3.0).angle_between(&Vector3f::new(1.0, 2.0, 3.0)),
+            0.0,
+        );
+        assert_float_eq(
+            Vector3f::new(0.1, 0.2, 0.3).angle_between(&Vector3f::new(0.1, 0.2, 0.3)),
+            0.0,
+        );
+        assert_float_eq(
+            Vector3f::new(1.0, 0.0, 0.0).angle_between(&Vector3f::new(-2.0, 0.0, 0.0)),
+            PI,
+        );
+    }
+
+    #[test]
+    fn test_vector3f_project_onto() {
+        assert_float_eq(
+            Vector3f::new(2.0, 2.0, 0.0).project_onto(&Vector3f::new(1.0, 0.0, 0.0)),
+            Vector3f::new(2.0, 0.0, 0.0),
+        );
+        assert_float_eq(
+            Vector3f::new(2.0, 2.0, 0.0).project_onto(&Vector3f::new(0.0, 0.0, 5.0)),
+            Vector3f::new(0.0, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn test_lerp() {
+        let a = Point3f::new(0.0, 2.0, -4.0);
+        let b = Point3f::new(4.0, 2.0, 4.0);
+        assert_float_eq(a.lerp(&b, 0.0), a);
+        assert_float_eq(a.lerp(&b, 1.0), b);
+        assert_float_eq(a.lerp(&b, 0.5), Point3f::new(2.0, 2.0, 0.0));
+
+        let a = Vector3f::new(1.0, 0.0, 0.0);
+        let b = Vector3f::new(0.0, 1.0, -2.0);
+        assert_float_eq(a.lerp(&b, 0.0), a);
+        assert_float_eq(a.lerp(&b, 1.0), b);
+        assert_float_eq(a.lerp(&b, 0.5), Vector3f::new(0.5, 0.5, -1.0));
+    }
+
     #[test]
     fn test_vec_get_xyzw() {
         let v = Vector4f {
@@ -1041,6 +1781,33 @@ mod tests {
         assert_eq!(v.z(), 3.0);
     }
 
+    #[test]
+    fn test_vec_index() {
+        let mut v = Vector4f::new(1.0, 2.0, 3.0, 4.0);
+        assert_eq!(v[0], v.x());
+        assert_eq!(v[3], v.w());
+        v[1] = 5.0;
+        assert_eq!(v.y(), 5.0);
+
+        let p = Point3f::new(1.0, 2.0, 3.0);
+        assert_eq!((p[0], p[1], p[2]), (1.0, 2.0, 3.0));
+
+        let v = Vector3f::new(4.0, 5.0, 6.0);
+        assert_eq!((v[0], v[1], v[2]), (4.0, 5.0, 6.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Index 4 is out of range for Vector4f")]
+    fn test_vector4f_index_out_of_range() {
+        let _ = Vector4f::new(1.0, 2.0, 3.0, 4.0)[4];
+    }
+
+    #[test]
+    #[should_panic(expected = "Index 3 is out of range for Vector3f")]
+    fn test_vector3f_index_out_of_range() {
+        let _ = Vector3f::new(1.0, 2.0, 3.0)[3];
+    }
+
     #[test]
     fn test_matrix_new() {
         let m = Matrix4x4f::new([
@@ -1075,6 +1842,89 @@ mod tests {
             });
     }
 
+    #[test]
+    fn test_vec_component_ops() {
+        let a = Vector3f::new(1.0, -2.0, 3.0);
+        let b = Vector3f::new(-1.0, 5.0, 2.0);
+        assert_eq!(a.component_min(&b), Vector3f::new(-1.0, -2.0, 2.0));
+        assert_eq!(a.component_max(&b), Vector3f::new(1.0, 5.0, 3.0));
+
+        assert_eq!(
+            Vector3f::new(-1.0, -2.5, 0.0).abs(),
+            Vector3f::new(1.0, 2.5, 0.0)
+        );
+        assert_eq!(Vector3f::new(1.0, -7.0, 3.0).max_component(), 7.0);
+        assert_eq!(Vector3f::new(1.0, 2.0, 3.0).max_component(), 3.0);
+
+        let p = Point3f::new(1.0, -2.0, 3.0);
+        let q = Point3f::new(-1.0, 5.0, 2.0);
+        assert_eq!(p.component_min(&q), Point3f::new(-1.0, -2.0, 2.0));
+        assert_eq!(p.component_max(&q), Point3f::new(1.0, 5.0, 3.0));
+    }
+
+    #[test]
+    fn test_vec_from_tuple() {
+        let t = (1.0, 2.0, 3.0);
+        let p: Point3f = t.into();
+        let v: Vector3f = t.into();
+        assert_eq!(p, Point3f::new(1.0, 2.0, 3.0));
+        assert_eq!(v, Vector3f::new(1.0, 2.0, 3.0));
+
+        // the target type decides whether w is 1 or 0
+        assert!(Vector4f::from(p).is_point3_tuple());
+        assert!(Vector4f::from(v).is_vector3_tuple());
+    }
+
+    #[test]
+    fn test_vec_display() {
+        assert_eq!(Point3f::new(1.0, -2.5, 3.0).to_string(), "(1, -2.5, 3)");
+        assert_eq!(Vector3f::new(0.0, 1.5, -4.0).to_string(), "<0, 1.5, -4>");
+    }
+
+    #[test]
+    fn test_matrix_display() {
+        assert_eq!(
+            Matrix2x2f::new([1.0, -2.5, 30.0, 4.0]).to_string(),
+            " 1 -2.5\n30    4"
+        );
+        assert_eq!(
+            Matrix4x4f::translation(Vector3f::new(5.0, -3.0, 2.0)).to_string(),
+            "1 0 0  5\n0 1 0 -3\n0 0 1  2\n0 0 0  1"
+        );
+    }
+
+    #[test]
+    fn test_matrix4x4f_from_rows_and_columns() {
+        let identity_rows = [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        assert_eq!(Matrix4x4f::from_rows(identity_rows), Matrix4x4f::identity());
+        assert_eq!(
+            Matrix4x4f::from_columns(identity_rows),
+            Matrix4x4f::identity()
+        );
+
+        let rows = [
+            [1.0, 2.0, 3.0, 4.0],
+            [5.5, 6.5, 7.5, 8.5],
+            [9.0, 10.0, 11.0, 12.0],
+            [13.5, 14.5, 15.5, 16.5],
+        ];
+        let m = Matrix4x4f::from_rows(rows);
+        assert_eq!(
+            m,
+            Matrix4x4f::new([
+                1.0, 2.0, 3.0, 4.0, 5.5, 6.5, 7.5, 8.5, 9.0, 10.0, 11.0, 12.0, 13.5, 14.5, 15.5,
+                16.5,
+            ])
+        );
+        assert_eq!(Matrix4x4f::from_columns(rows), m.transpose());
+        assert_eq!(Matrix4x4f::from_columns(rows).get(0, 1), 5.5);
+    }
+
     #[test]
     fn test_matrix_eq() {
         {
@@ -1130,6 +1980,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_matrix_elementwise_ops() {
+        assert_float_eq(
+            Matrix4x4f::identity() * 2.0,
+            Matrix4x4f::new([
+                2.0, 0.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0, 2.0,
+            ]),
+        );
+
+        let a = Matrix2x2f::new([1.0, 2.0, 3.0, 4.0]);
+        let b = Matrix2x2f::new([0.5, -1.0, 0.0, 2.0]);
+        assert_float_eq(a + b, Matrix2x2f::new([1.5, 1.0, 3.0, 6.0]));
+        assert_float_eq(a - b, Matrix2x2f::new([0.5, 3.0, 3.0, 2.0]));
+    }
+
+    #[test]
+    fn test_matrix_lerp() {
+        let i = Matrix4x4f::identity();
+        let m = Matrix4x4f::translation(Vector3f::new(4.0, -2.0, 0.0))
+            .scale(Vector3f::new(3.0, 3.0, 3.0));
+
+        assert_float_eq(i.lerp(&m, 0.0), i);
+        assert_float_eq(i.lerp(&m, 1.0), m);
+        assert_float_eq(
+            i.lerp(&m, 0.5) * Point3f::new(0.0, 0.0, 0.0),
+            Point3f::new(6.0, -3.0, 0.0),
+        );
+    }
+
     #[test]
     fn test_matrix4x4f_identity() {
         assert_float_eq(
@@ -1320,6 +2199,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_matrix4x4f_powi() {
+        use std::f64::consts::PI;
+
+        let m = Matrix4x4f::new([
+            -5.0, 2.0, 6.0, -8.0, 1.0, -5.0, 1.0, 8.0, 7.0, 7.0, -6.0, -7.0, 1.0, -3.0, 7.0, 4.0,
+        ]);
+        assert_float_eq(m.powi(0), Matrix4x4f::identity());
+        assert_float_eq(m.powi(1), m);
+        assert_float_eq_within(m.powi(3), m * m * m, 1e-9);
+        assert_float_eq_within(m.powi(-1), m.inverse().unwrap(), 1e-9);
+
+        let inv = m.inverse().unwrap();
+        assert_float_eq_within(m.powi(-2), inv * inv, 1e-9);
+
+        let step = Matrix4x4f::rotation_z(PI / 8.0);
+        assert_float_eq_within(step.powi(16), Matrix4x4f::identity(), 1e-9);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be invertible")]
+    fn test_matrix4x4f_powi_singular() {
+        Matrix4x4f::scaling(Vector3f::new(0.0, 1.0, 1.0)).powi(-1);
+    }
+
     #[test]
     fn test_translation() {
         let m = Matrix4x4f::translation(Vector3f::new(5.0, -3.0, 2.0));
@@ -1329,6 +2233,51 @@ mod tests {
 
         let v = Vector3f::new(-3.0, 4.0, 5.0);
         assert_float_eq(m * v, v);
+        // w would be non-zero here, but it is ignored for vectors
+        assert_float_eq(m.transpose() * v, v);
+    }
+
+    #[test]
+    fn test_point_vector_transform_shortcuts() {
+        use std::f64::consts::PI;
+
+        assert_float_eq(
+            Point3f::new(2.0, 3.0, 4.0).translated(Vector3f::new(5.0, -3.0, 2.0)),
+            Point3f::new(7.0, 0.0, 6.0),
+        );
+        assert_float_eq(
+            Point3f::new(-4.0, 6.0, 8.0).scaled(Vector3f::new(2.0, 3.0, 4.0)),
+            Point3f::new(-8.0, 18.0, 32.0),
+        );
+        assert_float_eq(
+            Point3f::new(0.0, 1.0, 0.0).rotated_x(PI / 2.0),
+            Point3f::new(0.0, 0.0, 1.0),
+        );
+        assert_float_eq(
+            Point3f::new(0.0, 0.0, 1.0).rotated_y(PI / 2.0),
+            Point3f::new(1.0, 0.0, 0.0),
+        );
+        assert_float_eq(
+            Point3f::new(0.0, 1.0, 0.0).rotated_z(PI / 2.0),
+            Point3f::new(-1.0, 0.0, 0.0),
+        );
+
+        assert_float_eq(
+            Vector3f::new(-4.0, 6.0, 8.0).scaled(Vector3f::new(2.0, 3.0, 4.0)),
+            Vector3f::new(-8.0, 18.0, 32.0),
+        );
+        assert_float_eq(
+            Vector3f::new(0.0, 1.0, 0.0).rotated_x(PI / 2.0),
+            Vector3f::new(0.0, 0.0, 1.0),
+        );
+        assert_float_eq(
+            Vector3f::new(0.0, 0.0, 1.0).rotated_y(PI / 2.0),
+            Vector3f::new(1.0, 0.0, 0.0),
+        );
+        assert_float_eq(
+            Vector3f::new(0.0, 1.0, 0.0).rotated_z(PI / 2.0),
+            Vector3f::new(-1.0, 0.0, 0.0),
+        );
     }
 
     #[test]
@@ -1411,6 +2360,45 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_orthographic() {
+        let m = Matrix4x4f::orthographic(-2.0, 2.0, -1.0, 1.0, 1.0, 11.0);
+
+        assert_float_eq(
+            m * Vector4f::new_point3_tuple(0.0, 0.0, -1.0),
+            Vector4f::new_point3_tuple(0.0, 0.0, -1.0),
+        );
+        assert_float_eq(
+            m * Vector4f::new_point3_tuple(2.0, 1.0, -11.0),
+            Vector4f::new_point3_tuple(1.0, 1.0, 1.0),
+        );
+        assert_float_eq(
+            m * Vector4f::new_point3_tuple(-1.0, -0.5, -6.0),
+            Vector4f::new_point3_tuple(-0.5, -0.5, 0.0),
+        );
+    }
+
+    #[test]
+    fn test_perspective() {
+        use std::f64::consts::PI;
+
+        let m = Matrix4x4f::perspective(PI / 2.0, 2.0, 1.0, 10.0);
+
+        let near = m * Vector4f::new_point3_tuple(0.0, 0.0, -1.0);
+        assert_float_eq(near / near.w(), Vector4f::new(0.0, 0.0, -1.0, 1.0));
+
+        let far = m * Vector4f::new_point3_tuple(0.0, 0.0, -10.0);
+        assert_float_eq(far / far.w(), Vector4f::new(0.0, 0.0, 1.0, 1.0));
+
+        // w grows with depth, so points further away are shrunk more
+        [1.0, 2.0, 5.0, 10.0].into_iter().for_each(|depth| {
+            let p = m * Vector4f::new_point3_tuple(2.0, 1.0, -depth);
+            assert_float_eq(p.w(), depth);
+            assert_float_eq(p.x() / p.w(), 1.0 / depth);
+            assert_float_eq(p.y() / p.w(), 1.0 / depth);
+        });
+    }
+
     #[test]
     fn test_transformation_fluent_api() {
         use std::f64::consts::PI;
@@ -1435,6 +2423,108 @@ mod tests {
         // shearing fluent API already tested in test_shearing()
     }
 
+    #[test]
+    fn test_sequence_cyclic() {
+        let mut seq = Sequence::cyclic(vec![0.5]);
+        assert_eq!(seq.next_f64(), 0.5);
+        assert_eq!(seq.next_f64(), 0.5);
+
+        let mut seq = Sequence::cyclic(vec![0.1, 0.5, 1.0]);
+        let vals = (0..4).map(|_| seq.next_f64()).collect::<Vec<_>>();
+        assert_eq!(vals, vec![0.1, 0.5, 1.0, 0.1]);
+    }
+
+    #[test]
+    fn test_sequence_seeded() {
+        let mut a = Sequence::seeded(42);
+        let mut b = Sequence::seeded(42);
+        let vals_a = (0..100).map(|_| a.next_f64()).collect::<Vec<_>>();
+        let vals_b = (0..100).map(|_| b.next_f64()).collect::<Vec<_>>();
+
+        assert_eq!(vals_a, vals_b);
+        assert!(vals_a.iter().all(|v| (0.0..1.0).contains(v)));
+        assert!(vals_a.windows(2).any(|w| w[0] != w[1]));
+
+        let mut c = Sequence::seeded(43);
+        assert_ne!(vals_a[0], c.next_f64());
+
+        let mut zero = Sequence::seeded(0);
+        assert_ne!(zero.next_f64(), zero.next_f64());
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one value")]
+    fn test_sequence_cyclic_empty() {
+        Sequence::cyclic(vec![]);
+    }
+
+    #[test]
+    fn test_matrix_array_conversions() {
+        let rows = [
+            [1.0, 2.0, 3.0, 4.0],
+            [5.5, 6.5, 7.5, 8.5],
+            [9.0, 10.0, 11.0, 12.0],
+            [13.5, 14.5, 15.5, 16.5],
+        ];
+        let m: Matrix4x4f = rows.into();
+        assert_eq!(m, Matrix4x4f::from_rows(rows));
+        assert_eq!(m.get(1, 2), 7.5);
+        let back: [[f64; 4]; 4] = m.into();
+        assert_eq!(back, rows);
+
+        let flat = [
+            1.0, 2.0, 3.0, 4.0, 5.5, 6.5, 7.5, 8.5, 9.0, 10.0, 11.0, 12.0, 13.5, 14.5, 15.5, 16.5,
+        ];
+        assert_eq!(Matrix4x4f::from(flat), Matrix4x4f::new(flat));
+        assert_eq!(Matrix4x4f::from(flat), m);
+        let back: [f64; 16] = Matrix4x4f::new(flat).into();
+        assert_eq!(back, flat);
+    }
+
+    #[test]
+    fn test_matrix_is_identity() {
+        assert!(Matrix4x4f::identity().is_identity());
+        assert!(Matrix3x3f::identity().is_identity());
+        assert!(!Matrix4x4f::translation(Vector3f::new(1.0, 0.0, 0.0)).is_identity());
+
+        let m = Matrix4x4f::scaling(Vector3f::new(2.0, 2.0, 2.0));
+        assert!((m * m.inverse().unwrap()).is_identity());
+    }
+
+    #[test]
+    fn test_matrix_approx_equals() {
+        let a = Matrix4x4f::identity();
+        let b = Matrix4x4f::translation(Vector3f::new(0.001, 0.0, -0.001));
+
+        assert!(a.approx_equals(&b, 0.01));
+        assert!(!a.approx_equals(&b, 0.0001));
+        assert!(!a.float_eq(&b));
+
+        // a difference of exactly eps is still equal, same as float_eq_within()
+        assert!(a.approx_equals(&b, 0.001));
+        assert!(a.float_eq_within(&b, 0.001));
+    }
+
+    #[test]
+    fn test_transform_builder() {
+        use std::f64::consts::PI;
+
+        let v = Vector3f::new(10.0, 5.0, 7.0);
+        let p = Point3f::new(1.0, 0.0, 0.0);
+        let m = TransformBuilder::new()
+            .rotate_z(PI / 2.0)
+            .translate(v)
+            .build();
+
+        assert_float_eq(
+            m,
+            Matrix4x4f::translation(v) * Matrix4x4f::rotation_z(PI / 2.0),
+        );
+        assert_float_eq(m * p, Point3f::new(10.0, 6.0, 7.0));
+        assert_float_eq(m, Matrix4x4f::identity().rotate_z(PI / 2.0).translate(v));
+        assert_float_eq(TransformBuilder::new().build(), Matrix4x4f::identity());
+    }
+
     #[test]
     fn test_vector3f_reflect() {
         assert_eq!(
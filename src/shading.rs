@@ -1,12 +1,19 @@
+use std::fmt;
+
 use crate::{
-    graphics::Color,
-    math::{Point3f, Vector3f},
+    geometry::Ray,
+    graphics::{Canvas, Color, WrapMode},
+    math::{FloatEq, Point3f, Vector3f},
 };
 
-#[derive(Copy, Clone)]
+#[derive(PartialEq, Debug, Copy, Clone)]
 pub struct PointLight {
     pub position: Point3f,
     pub intensity: Color,
+    // attenuation = 1 / (constant + linear * d + quadratic * d^2), d = distance to light
+    pub constant: f64,
+    pub linear: f64,
+    pub quadratic: f64,
 }
 
 impl PointLight {
@@ -14,8 +21,224 @@ impl PointLight {
         Self {
             position,
             intensity,
+            constant: 1.0,
+            linear: 0.0,
+            quadratic: 0.0,
+        }
+    }
+
+    pub fn with_attenuation(self, constant: f64, linear: f64, quadratic: f64) -> Self {
+        Self {
+            constant,
+            linear,
+            quadratic,
+            ..self
         }
     }
+
+    pub fn attenuation(&self, point: &Point3f) -> f64 {
+        let d = (self.position - *point).magnitude();
+        1.0 / (self.constant + self.linear * d + self.quadratic * d * d)
+    }
+}
+
+impl FloatEq for PointLight {
+    fn float_eq(&self, other: &Self) -> bool {
+        self.float_eq_within(other, f64::EPSILON)
+    }
+
+    fn float_eq_within(&self, other: &Self, eps: f64) -> bool {
+        self.position.float_eq_within(&other.position, eps)
+            && self.intensity.float_eq_within(&other.intensity, eps)
+            && self.constant.float_eq_within(&other.constant, eps)
+            && self.linear.float_eq_within(&other.linear, eps)
+            && self.quadratic.float_eq_within(&other.quadratic, eps)
+    }
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct SpotLight {
+    pub position: Point3f,
+    pub direction: Vector3f,
+    pub intensity: Color,
+    // angles are in radians, measured from the direction of the spotlight
+    // full intensity within inner_angle
+    pub inner_angle: f64,
+    // no light beyond outer_angle
+    pub outer_angle: f64,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point3f,
+        direction: Vector3f,
+        intensity: Color,
+        inner_angle: f64,
+        outer_angle: f64,
+    ) -> Self {
+        Self {
+            position,
+            direction,
+            intensity,
+            inner_angle,
+            outer_angle,
+        }
+    }
+
+    /// 1.0 inside the inner cone, 0.0 outside the outer cone, and smoothly
+    /// interpolated in between. A point at the light's position has no angle, so it gets
+    /// no light.
+    pub fn falloff(&self, point: &Point3f) -> f64 {
+        let offset = *point - self.position;
+        if offset.magnitude().float_eq(&0.0) {
+            return 0.0;
+        }
+
+        let angle = self.direction.angle_between(&offset);
+
+        if angle <= self.inner_angle {
+            1.0
+        } else if angle >= self.outer_angle {
+            0.0
+        } else {
+            let t = (self.outer_angle - angle) / (self.outer_angle - self.inner_angle);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+impl FloatEq for SpotLight {
+    fn float_eq(&self, other: &Self) -> bool {
+        self.float_eq_within(other, f64::EPSILON)
+    }
+
+    fn float_eq_within(&self, other: &Self, eps: f64) -> bool {
+        self.position.float_eq_within(&other.position, eps)
+            && self.direction.float_eq_within(&other.direction, eps)
+            && self.intensity.float_eq_within(&other.intensity, eps)
+            && self.inner_angle.float_eq_within(&other.inner_angle, eps)
+            && self.outer_angle.float_eq_within(&other.outer_angle, eps)
+    }
+}
+
+/// Light that is infinitely far away (e.g. the sun), so all of its rays travel in
+/// the same direction
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct DirectionalLight {
+    pub direction: Vector3f,
+    pub intensity: Color,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vector3f, intensity: Color) -> Self {
+        Self {
+            direction,
+            intensity,
+        }
+    }
+}
+
+impl FloatEq for DirectionalLight {
+    fn float_eq(&self, other: &Self) -> bool {
+        self.float_eq_within(other, f64::EPSILON)
+    }
+
+    fn float_eq_within(&self, other: &Self, eps: f64) -> bool {
+        self.direction.float_eq_within(&other.direction, eps)
+            && self.intensity.float_eq_within(&other.intensity, eps)
+    }
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Light {
+    Point(PointLight),
+    Spot(SpotLight),
+    Directional(DirectionalLight),
+}
+
+impl Light {
+    pub fn intensity(&self) -> Color {
+        match self {
+            Light::Point(light) => light.intensity,
+            Light::Spot(light) => light.intensity,
+            Light::Directional(light) => light.intensity,
+        }
+    }
+
+    /// Normalized direction from the point towards the light. This is a zero vector if
+    /// there is no direction, i.e. the point is at the light's position (or a directional
+    /// light has no direction), so the point gets no diffuse light.
+    pub fn lightv(&self, point: &Point3f) -> Vector3f {
+        let lightv = match self {
+            Light::Point(light) => light.position - *point,
+            Light::Spot(light) => light.position - *point,
+            Light::Directional(light) => -light.direction,
+        };
+        lightv
+            .try_normalize()
+            .unwrap_or(Vector3f::new(0.0, 0.0, 0.0))
+    }
+
+    /// Distance from the point to the light, i.e. how far a shadow ray has to travel
+    pub fn distance(&self, point: &Point3f) -> f64 {
+        match self {
+            Light::Point(light) => (light.position - *point).magnitude(),
+            Light::Spot(light) => (light.position - *point).magnitude(),
+            Light::Directional(_) => f64::INFINITY,
+        }
+    }
+
+    /// How much of the light's intensity reaches the point, usually between 0.0 and 1.0
+    pub fn falloff(&self, point: &Point3f) -> f64 {
+        match self {
+            Light::Point(light) => light.attenuation(point),
+            Light::Spot(light) => light.falloff(point),
+            Light::Directional(_) => 1.0,
+        }
+    }
+}
+
+impl FloatEq for Light {
+    fn float_eq(&self, other: &Self) -> bool {
+        self.float_eq_within(other, f64::EPSILON)
+    }
+
+    /// Lights of different kinds are never equal
+    fn float_eq_within(&self, other: &Self, eps: f64) -> bool {
+        match (self, other) {
+            (Light::Point(a), Light::Point(b)) => a.float_eq_within(b, eps),
+            (Light::Spot(a), Light::Spot(b)) => a.float_eq_within(b, eps),
+            (Light::Directional(a), Light::Directional(b)) => a.float_eq_within(b, eps),
+            _ => false,
+        }
+    }
+}
+
+impl From<PointLight> for Light {
+    fn from(value: PointLight) -> Self {
+        Light::Point(value)
+    }
+}
+
+impl From<SpotLight> for Light {
+    fn from(value: SpotLight) -> Self {
+        Light::Spot(value)
+    }
+}
+
+impl From<DirectionalLight> for Light {
+    fn from(value: DirectionalLight) -> Self {
+        Light::Directional(value)
+    }
+}
+
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub enum SpecularModel {
+    // uses the reflection of the light vector
+    #[default]
+    Phong,
+    // uses the halfway vector between the light and eye vectors
+    BlinnPhong,
 }
 
 // phong shading material
@@ -27,9 +250,68 @@ pub struct Material {
     pub specular: f64,
     // best range = 10 (large) to 200 (small)
     pub shininess: f64,
+    // 0 = not reflective, 1 = perfect mirror
+    pub reflective: f64,
+    // 0 = opaque, 1 = fully transparent
+    pub transparency: f64,
+    pub refractive_index: f64,
+    pub specular_model: SpecularModel,
+    // light given off by the material itself, regardless of any light sources
+    pub emission: Color,
+    // offset added to the surface normal at a (world space) point before shading, which
+    // makes flat surfaces look bumpy
+    pub normal_perturbation: Option<NormalPerturbation>,
 }
 
+/// Offset to add to the surface normal at a point, see `Material::perturb_normal()`.
+/// Perturbations are equal if they use the same function.
+#[derive(Debug, Copy, Clone)]
+pub struct NormalPerturbation(pub fn(&Point3f) -> Vector3f);
+
+impl PartialEq for NormalPerturbation {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 as usize == other.0 as usize
+    }
+}
+
+/// A material field that is outside of its physically sensible range, with the
+/// offending value
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum MaterialError {
+    Ambient(f64),
+    Diffuse(f64),
+    Specular(f64),
+    Shininess(f64),
+    Reflective(f64),
+    Transparency(f64),
+}
+
+impl fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaterialError::Ambient(v) => write!(f, "ambient {} is not within 0..=1", v),
+            MaterialError::Diffuse(v) => write!(f, "diffuse {} is not within 0..=1", v),
+            MaterialError::Specular(v) => write!(f, "specular {} is not within 0..=1", v),
+            MaterialError::Shininess(v) => write!(f, "shininess {} is not positive", v),
+            MaterialError::Reflective(v) => write!(f, "reflective {} is not within 0..=1", v),
+            MaterialError::Transparency(v) => {
+                write!(f, "transparency {} is not within 0..=1", v)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MaterialError {}
+
 impl Material {
+    // refractive indices of common materials
+    pub const VACUUM_IOR: f64 = 1.0;
+    pub const AIR_IOR: f64 = 1.00029;
+    pub const WATER_IOR: f64 = 1.33;
+    pub const GLASS_IOR: f64 = 1.5;
+    pub const DIAMOND_IOR: f64 = 2.417;
+
+    /// Does not validate its arguments, see `try_new()`
     pub fn new(color: Color, ambient: f64, diffuse: f64, specular: f64, shininess: f64) -> Self {
         Self {
             color,
@@ -37,8 +319,109 @@ impl Material {
             diffuse,
             specular,
             shininess,
+            ..Default::default()
+        }
+    }
+
+    /// Same as `new()`, but fails if any of the fields are out of range
+    pub fn try_new(
+        color: Color,
+        ambient: f64,
+        diffuse: f64,
+        specular: f64,
+        shininess: f64,
+    ) -> Result<Self, MaterialError> {
+        let material = Self::new(color, ambient, diffuse, specular, shininess);
+        material.validate()?;
+        Ok(material)
+    }
+
+    /// Checks that ambient, diffuse, specular, reflective and transparency are within
+    /// `0..=1`, and that shininess is positive. The first invalid field is reported.
+    pub fn validate(&self) -> Result<(), MaterialError> {
+        let unit = |v: f64| (0.0..=1.0).contains(&v);
+
+        if !unit(self.ambient) {
+            Err(MaterialError::Ambient(self.ambient))
+        } else if !unit(self.diffuse) {
+            Err(MaterialError::Diffuse(self.diffuse))
+        } else if !unit(self.specular) {
+            Err(MaterialError::Specular(self.specular))
+        } else if self.shininess.is_nan() || self.shininess <= 0.0 {
+            Err(MaterialError::Shininess(self.shininess))
+        } else if !unit(self.reflective) {
+            Err(MaterialError::Reflective(self.reflective))
+        } else if !unit(self.transparency) {
+            Err(MaterialError::Transparency(self.transparency))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn glass() -> Self {
+        Self {
+            transparency: 1.0,
+            refractive_index: Self::GLASS_IOR,
+            ..Default::default()
+        }
+    }
+
+    pub fn water() -> Self {
+        Self {
+            transparency: 1.0,
+            refractive_index: Self::WATER_IOR,
+            ..Default::default()
+        }
+    }
+
+    pub fn mirror() -> Self {
+        Self {
+            color: Color::BLACK,
+            diffuse: 0.1,
+            reflective: 0.9,
+            ..Default::default()
         }
     }
+
+    /// Normal to shade with at the point, after applying `normal_perturbation`. Falls back
+    /// to the unperturbed normal if the perturbation cancels it out.
+    pub fn perturb_normal(&self, point: &Point3f, normalv: &Vector3f) -> Vector3f {
+        match self.normal_perturbation {
+            Some(NormalPerturbation(perturbation)) => (*normalv + perturbation(point))
+                .try_normalize()
+                .unwrap_or(*normalv),
+            None => *normalv,
+        }
+    }
+
+    pub fn matte() -> Self {
+        Self {
+            specular: 0.0,
+            ..Default::default()
+        }
+    }
+}
+
+impl FloatEq for Material {
+    fn float_eq(&self, other: &Self) -> bool {
+        self.float_eq_within(other, f64::EPSILON)
+    }
+
+    fn float_eq_within(&self, other: &Self, eps: f64) -> bool {
+        self.color.float_eq_within(&other.color, eps)
+            && self.ambient.float_eq_within(&other.ambient, eps)
+            && self.diffuse.float_eq_within(&other.diffuse, eps)
+            && self.specular.float_eq_within(&other.specular, eps)
+            && self.shininess.float_eq_within(&other.shininess, eps)
+            && self.reflective.float_eq_within(&other.reflective, eps)
+            && self.transparency.float_eq_within(&other.transparency, eps)
+            && self
+                .refractive_index
+                .float_eq_within(&other.refractive_index, eps)
+            && self.specular_model == other.specular_model
+            && self.emission.float_eq_within(&other.emission, eps)
+            && self.normal_perturbation == other.normal_perturbation
+    }
 }
 
 impl Default for Material {
@@ -49,48 +432,161 @@ impl Default for Material {
             diffuse: 0.9,
             specular: 0.9,
             shininess: 200.0,
+            reflective: 0.0,
+            transparency: 0.0,
+            refractive_index: Material::VACUUM_IOR,
+            specular_model: SpecularModel::Phong,
+            emission: Color::BLACK,
+            normal_perturbation: None,
+        }
+    }
+}
+
+/// Color for rays that do not hit anything
+#[derive(PartialEq, Debug, Clone)]
+pub enum Background {
+    Solid(Color),
+    // blends from bottom (ray pointing straight down) to top (ray pointing straight up)
+    Gradient { top: Color, bottom: Color },
+    Skybox(SkyboxTextures),
+}
+
+/// One canvas per cube face, named after the axis the face is centered on. Faces are
+/// oriented as seen from inside the cube, following the OpenGL cube map convention
+/// (e.g. the top of every side face points towards +y).
+#[derive(PartialEq, Debug, Clone)]
+pub struct SkyboxTextures {
+    pub pos_x: Canvas,
+    pub neg_x: Canvas,
+    pub pos_y: Canvas,
+    pub neg_y: Canvas,
+    pub pos_z: Canvas,
+    pub neg_z: Canvas,
+}
+
+impl SkyboxTextures {
+    pub fn sample(&self, direction: &Vector3f) -> Color {
+        let (x, y, z) = (direction.x(), direction.y(), direction.z());
+        let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+
+        // face hit by the direction, and the (s, t) coordinates within it in -1..1
+        let (face, s, t) = if ax >= ay && ax >= az {
+            if x > 0.0 {
+                (&self.pos_x, -z / ax, -y / ax)
+            } else {
+                (&self.neg_x, z / ax, -y / ax)
+            }
+        } else if ay >= az {
+            if y > 0.0 {
+                (&self.pos_y, x / ay, z / ay)
+            } else {
+                (&self.neg_y, x / ay, -z / ay)
+            }
+        } else if z > 0.0 {
+            (&self.pos_z, x / az, -y / az)
+        } else {
+            (&self.neg_z, -x / az, -y / az)
+        };
+
+        face.sample_uv((s + 1.0) / 2.0, (t + 1.0) / 2.0, WrapMode::Clamp)
+    }
+}
+
+impl Background {
+    pub fn color_for(&self, ray: &Ray) -> Color {
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { top, bottom } => {
+                // a ray without a direction gets the color halfway between
+                let y = ray.get_direction().try_normalize().map_or(0.0, |d| d.y());
+                let t = (y + 1.0) / 2.0;
+                bottom.lerp(top, t)
+            }
+            Background::Skybox(textures) => textures.sample(&ray.get_direction()),
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Solid(Color::BLACK)
+    }
+}
+
+/// Atmospheric fog, which blends a hit's color towards the fog color the further away
+/// it is
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Fog {
+    pub color: Color,
+    pub density: f64,
+}
+
+impl Fog {
+    pub fn new(color: Color, density: f64) -> Self {
+        Self { color, density }
+    }
+
+    /// Color seen through the fog, for a surface of the given color at distance `t`.
+    /// Misses (infinite `t`) become the fog color.
+    pub fn apply(&self, color: Color, t: f64) -> Color {
+        if self.density <= 0.0 {
+            return color;
         }
+
+        let amount = 1.0 - (-self.density * t).exp();
+        color.lerp(&self.color, amount)
     }
 }
 
 pub struct LightingArgs {
     pub material: Material,
-    pub light: PointLight,
+    pub light: Light,
     pub point: Point3f,
     pub eyev: Vector3f,
     pub normalv: Vector3f,
+    // fraction of the light that reaches the point (0 = fully in shadow, 1 = fully lit),
+    // which scales diffuse and specular but not ambient
+    pub light_intensity: f64,
 }
 
 pub fn lighting(args: LightingArgs) -> Color {
-    let effective_color = args.material.color * args.light.intensity;
-    let lightv = (args.light.position - args.point).normalize();
+    let effective_color = args.material.color * args.light.intensity();
+    let lightv = args.light.lightv(&args.point);
     let ambient = effective_color * args.material.ambient;
+    let normalv = args.material.perturb_normal(&args.point, &args.normalv);
 
-    let light_dot_normal = lightv.dot(&args.normalv);
+    let light_dot_normal = lightv.dot(&normalv);
+    let falloff = args.light.falloff(&args.point) * args.light_intensity;
 
-    let (diffuse, specular) = if light_dot_normal < 0.0 {
-        // light is on the other side
+    let (diffuse, specular) = if light_dot_normal < 0.0 || falloff <= 0.0 {
+        // light is on the other side of the surface, or none of it reaches the point
+        // (shadowed, outside of a spotlight's cone or fully attenuated)
         (Color::BLACK, Color::BLACK)
     } else {
         let diffuse = effective_color * args.material.diffuse * light_dot_normal;
-        let reflectv = (-lightv).reflect(&args.normalv);
-        let reflect_dot_eye = reflectv.dot(&args.eyev);
+        let reflect_dot_eye = match args.material.specular_model {
+            SpecularModel::Phong => (-lightv).reflect(&normalv).dot(&args.eyev),
+            SpecularModel::BlinnPhong => (lightv + args.eyev)
+                .try_normalize()
+                .map_or(0.0, |halfway| halfway.dot(&normalv)),
+        };
 
         let specular = if reflect_dot_eye <= 0.0 {
             Color::BLACK
         } else {
             let factor = reflect_dot_eye.powf(args.material.shininess);
-            args.light.intensity * args.material.specular * factor
+            args.light.intensity() * args.material.specular * factor
         };
 
-        (diffuse, specular)
+        (diffuse * falloff, specular * falloff)
     };
 
-    ambient + diffuse + specular
+    args.material.emission + ambient + diffuse + specular
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::math::{assert_float_eq, assert_float_ne};
 
     use super::*;
 
@@ -99,6 +595,119 @@ mod tests {
         let light = PointLight::new(Point3f::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
         assert_eq!(light.position, Point3f::new(0.0, 0.0, 0.0));
         assert_eq!(light.intensity, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(
+            (light.constant, light.linear, light.quadratic),
+            (1.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_pointlight_eq() {
+        let light = PointLight::new(Point3f::new(0.0, 0.0, -10.0), Color::WHITE);
+        assert_eq!(
+            light,
+            PointLight::new(Point3f::new(0.0, 0.0, -10.0), Color::WHITE)
+        );
+        assert_float_eq(
+            light,
+            PointLight::new(Point3f::new(0.1 + 0.2 - 0.3, 0.0, -10.0), Color::WHITE),
+        );
+        assert_float_ne(
+            light,
+            PointLight::new(Point3f::new(0.0, 0.0, 10.0), Color::WHITE),
+        );
+        assert_float_ne(
+            light,
+            PointLight::new(Point3f::new(0.0, 0.0, -10.0), Color::new(0.5, 0.5, 0.5)),
+        );
+        assert_float_ne(light, light.with_attenuation(1.0, 0.5, 0.0));
+    }
+
+    #[test]
+    fn test_spotlight_eq() {
+        let spot = |inner_angle: f64| {
+            SpotLight::new(
+                Point3f::new(0.0, 0.0, -10.0),
+                Vector3f::new(0.0, 0.0, 1.0),
+                Color::WHITE,
+                inner_angle,
+                0.5,
+            )
+        };
+        assert_eq!(spot(0.25), spot(0.25));
+        assert_float_eq(spot(0.25), spot(0.1 + 0.15));
+        assert_float_ne(spot(0.25), spot(0.3));
+    }
+
+    #[test]
+    fn test_light_eq() {
+        let position = Point3f::new(0.0, 0.0, -10.0);
+        let point: Light = PointLight::new(position, Color::WHITE).into();
+        let spot: Light = SpotLight::new(
+            position,
+            Vector3f::new(0.0, 0.0, 1.0),
+            Color::WHITE,
+            0.25,
+            0.5,
+        )
+        .into();
+        let directional: Light =
+            DirectionalLight::new(Vector3f::new(0.0, 0.0, 1.0), Color::WHITE).into();
+
+        assert_eq!(point, PointLight::new(position, Color::WHITE).into());
+        assert_ne!(point, spot);
+        [point, spot, directional].into_iter().for_each(|light| {
+            assert_float_eq(light, light);
+        });
+        assert_float_ne(point, spot);
+        assert_float_ne(spot, directional);
+        assert_float_ne(
+            point,
+            PointLight::new(position, Color::new(0.5, 0.5, 0.5)).into(),
+        );
+    }
+
+    #[test]
+    fn test_directionallight_eq() {
+        let light = DirectionalLight::new(Vector3f::new(1.0, -1.0, 0.0), Color::WHITE);
+        assert_eq!(
+            light,
+            DirectionalLight::new(Vector3f::new(1.0, -1.0, 0.0), Color::WHITE)
+        );
+        assert_float_eq(
+            light,
+            DirectionalLight::new(Vector3f::new(1.0, 0.1 + 0.2 - 1.3, 0.0), Color::WHITE),
+        );
+        assert_float_ne(
+            light,
+            DirectionalLight::new(Vector3f::new(0.0, -1.0, 0.0), Color::WHITE),
+        );
+        assert_float_ne(
+            light,
+            DirectionalLight::new(Vector3f::new(1.0, -1.0, 0.0), Color::BLACK),
+        );
+    }
+
+    #[test]
+    fn test_pointlight_attenuation() {
+        let light = PointLight::new(Point3f::new(0.0, 0.0, -10.0), Color::WHITE);
+        assert_eq!(light.attenuation(&Point3f::new(0.0, 0.0, 10.0)), 1.0);
+
+        let light = light.with_attenuation(0.0, 0.0, 1.0);
+        let contribution = |z: f64| {
+            lighting(LightingArgs {
+                material: Material::default(),
+                light: light.into(),
+                point: Point3f::new(0.0, 0.0, z),
+                eyev: Vector3f::new(0.0, 0.0, -1.0),
+                normalv: Vector3f::new(0.0, 0.0, -1.0),
+                light_intensity: 1.0,
+            }) - Color::new(0.1, 0.1, 0.1)
+        };
+
+        // twice as far away
+        assert_float_eq(contribution(-6.0) * 0.25, contribution(-2.0));
+        assert_float_eq(contribution(-9.0), Color::new(1.8, 1.8, 1.8));
     }
 
     #[test]
@@ -107,6 +716,110 @@ mod tests {
             Material::default(),
             Material::new(Color::new(1.0, 1.0, 1.0), 0.1, 0.9, 0.9, 200.0)
         );
+
+        let m = Material::default();
+        assert_eq!(m.reflective, 0.0);
+        assert_eq!(m.transparency, 0.0);
+        assert_eq!(m.refractive_index, 1.0);
+    }
+
+    #[test]
+    fn test_material_try_new() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        assert_eq!(
+            Material::try_new(white, 0.1, 0.9, 0.9, 200.0),
+            Ok(Material::default())
+        );
+        assert!(Material::try_new(white, 0.0, 1.0, 0.0, 0.5).is_ok());
+
+        assert_eq!(
+            Material::try_new(white, 2.0, 0.9, 0.9, 200.0),
+            Err(MaterialError::Ambient(2.0))
+        );
+        assert_eq!(
+            Material::try_new(white, 0.1, -0.1, 0.9, 200.0),
+            Err(MaterialError::Diffuse(-0.1))
+        );
+        assert_eq!(
+            Material::try_new(white, 0.1, 0.9, 1.5, 200.0),
+            Err(MaterialError::Specular(1.5))
+        );
+        assert_eq!(
+            Material::try_new(white, 0.1, 0.9, 0.9, -5.0),
+            Err(MaterialError::Shininess(-5.0))
+        );
+        assert_eq!(
+            Material::try_new(white, 0.1, 0.9, 0.9, 0.0),
+            Err(MaterialError::Shininess(0.0))
+        );
+        assert!(Material::try_new(white, f64::NAN, 0.9, 0.9, 200.0).is_err());
+    }
+
+    #[test]
+    fn test_material_validate() {
+        assert_eq!(Material::default().validate(), Ok(()));
+        assert_eq!(Material::glass().validate(), Ok(()));
+        assert_eq!(Material::mirror().validate(), Ok(()));
+
+        let m = Material {
+            reflective: 1.1,
+            ..Default::default()
+        };
+        assert_eq!(m.validate(), Err(MaterialError::Reflective(1.1)));
+
+        let m = Material {
+            transparency: -1.0,
+            ..Default::default()
+        };
+        assert_eq!(m.validate(), Err(MaterialError::Transparency(-1.0)));
+        assert_eq!(
+            m.validate().unwrap_err().to_string(),
+            "transparency -1 is not within 0..=1"
+        );
+    }
+
+    #[test]
+    fn test_material_float_eq() {
+        let a = Material {
+            ambient: 0.1 + 0.2,
+            ..Default::default()
+        };
+        let b = Material {
+            ambient: 0.3,
+            ..Default::default()
+        };
+        assert_ne!(a, b);
+        assert_float_eq(a, b);
+        assert_float_ne(a, Material::default());
+    }
+
+    #[test]
+    fn test_material_ior_constants() {
+        assert_eq!(Material::VACUUM_IOR, 1.0);
+        assert_eq!(Material::AIR_IOR, 1.00029);
+        assert_eq!(Material::WATER_IOR, 1.33);
+        assert_eq!(Material::GLASS_IOR, 1.5);
+        assert_eq!(Material::DIAMOND_IOR, 2.417);
+    }
+
+    #[test]
+    fn test_material_presets() {
+        let glass = Material::glass();
+        assert_eq!(glass.transparency, 1.0);
+        assert_eq!(glass.refractive_index, Material::GLASS_IOR);
+
+        let water = Material::water();
+        assert_eq!(water.transparency, 1.0);
+        assert_eq!(water.refractive_index, Material::WATER_IOR);
+        assert_eq!(Material::default().refractive_index, Material::VACUUM_IOR);
+
+        let mirror = Material::mirror();
+        assert!(mirror.reflective > 0.5);
+        assert_eq!(mirror.transparency, 0.0);
+
+        let matte = Material::matte();
+        assert_eq!(matte.specular, 0.0);
+        assert_eq!(matte.reflective, 0.0);
     }
 
     #[test]
@@ -119,7 +832,9 @@ mod tests {
             lighting(LightingArgs {
                 eyev: Vector3f::new(0.0, 0.0, -1.0),
                 normalv,
-                light: PointLight::new(Point3f::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0)),
+                light_intensity: 1.0,
+                light: PointLight::new(Point3f::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0))
+                    .into(),
                 material,
                 point: position,
             }),
@@ -130,7 +845,9 @@ mod tests {
             lighting(LightingArgs {
                 eyev: Vector3f::new(0.0, 2_f64.sqrt() / 2.0, -2_f64.sqrt() / 2.0),
                 normalv,
-                light: PointLight::new(Point3f::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0)),
+                light_intensity: 1.0,
+                light: PointLight::new(Point3f::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0))
+                    .into(),
                 material,
                 point: position,
             }),
@@ -141,7 +858,9 @@ mod tests {
             lighting(LightingArgs {
                 eyev: Vector3f::new(0.0, 0.0, -1.0),
                 normalv,
-                light: PointLight::new(Point3f::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)),
+                light_intensity: 1.0,
+                light: PointLight::new(Point3f::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0))
+                    .into(),
                 material,
                 point: position,
             }),
@@ -152,7 +871,9 @@ mod tests {
             lighting(LightingArgs {
                 eyev: Vector3f::new(0.0, -2_f64.sqrt() / 2.0, -2_f64.sqrt() / 2.0),
                 normalv,
-                light: PointLight::new(Point3f::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)),
+                light_intensity: 1.0,
+                light: PointLight::new(Point3f::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0))
+                    .into(),
                 material,
                 point: position,
             }),
@@ -163,11 +884,357 @@ mod tests {
             lighting(LightingArgs {
                 eyev: Vector3f::new(0.0, 0.0, -1.0),
                 normalv,
-                light: PointLight::new(Point3f::new(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0)),
+                light_intensity: 1.0,
+                light: PointLight::new(Point3f::new(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0))
+                    .into(),
                 material,
                 point: position,
             }),
             Color::new(0.1, 0.1, 0.1)
         );
     }
+
+    #[test]
+    fn test_lighting_light_intensity() {
+        let light_with = |light_intensity: f64| {
+            lighting(LightingArgs {
+                material: Material::default(),
+                light: PointLight::new(Point3f::new(0.0, 0.0, -10.0), Color::WHITE).into(),
+                point: Point3f::new(0.0, 0.0, 0.0),
+                eyev: Vector3f::new(0.0, 0.0, -1.0),
+                normalv: Vector3f::new(0.0, 0.0, -1.0),
+                light_intensity,
+            })
+        };
+
+        // fully lit: ambient 0.1 + diffuse 0.9 + specular 0.9
+        assert_float_eq(light_with(1.0), Color::new(1.9, 1.9, 1.9));
+        // in shadow, only ambient is left
+        assert_float_eq(light_with(0.0), Color::new(0.1, 0.1, 0.1));
+        assert_float_eq(light_with(0.5), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_lighting_emission() {
+        let glow = Color::new(0.5, 0.0, 0.0);
+        let material = Material {
+            emission: glow,
+            ..Default::default()
+        };
+        let light_with = |material: Material, intensity: Color| {
+            lighting(LightingArgs {
+                material,
+                light: PointLight::new(Point3f::new(0.0, 0.0, -10.0), intensity).into(),
+                point: Point3f::new(0.0, 0.0, 0.0),
+                eyev: Vector3f::new(0.0, 0.0, -1.0),
+                normalv: Vector3f::new(0.0, 0.0, -1.0),
+                light_intensity: 1.0,
+            })
+        };
+
+        assert_float_eq(light_with(material, Color::BLACK), glow);
+        assert_float_eq(
+            light_with(material, Color::WHITE),
+            light_with(Material::default(), Color::WHITE) + glow,
+        );
+        assert_eq!(Material::default().emission, Color::BLACK);
+    }
+
+    #[test]
+    fn test_lighting_normal_perturbation() {
+        fn none(_: &Point3f) -> Vector3f {
+            Vector3f::new(0.0, 0.0, 0.0)
+        }
+        fn ripples(point: &Point3f) -> Vector3f {
+            Vector3f::new(0.3 * (point.x() * 5.0).sin(), 0.0, 0.0)
+        }
+
+        // flat surface facing the eye, lit at an angle so that it is evenly shaded
+        let light_at = |material: Material, point: Point3f| {
+            lighting(LightingArgs {
+                material,
+                light: DirectionalLight::new(Vector3f::new(1.0, -1.0, 1.0), Color::WHITE).into(),
+                point,
+                eyev: Vector3f::new(0.0, 0.0, -1.0),
+                normalv: Vector3f::new(0.0, 0.0, -1.0),
+                light_intensity: 1.0,
+            })
+        };
+        let with = |perturbation: fn(&Point3f) -> Vector3f| Material {
+            normal_perturbation: Some(NormalPerturbation(perturbation)),
+            ..Default::default()
+        };
+        let p1 = Point3f::new(0.3, 0.0, 0.0);
+        let p2 = Point3f::new(0.9, 0.0, 0.0);
+
+        assert_float_eq(light_at(with(none), p1), light_at(Material::default(), p1));
+        assert_float_eq(
+            light_at(Material::default(), p1),
+            light_at(Material::default(), p2),
+        );
+        assert_float_ne(
+            light_at(with(ripples), p1),
+            light_at(Material::default(), p1),
+        );
+        assert_float_ne(light_at(with(ripples), p1), light_at(with(ripples), p2));
+
+        // cancelling the normal out keeps the original normal
+        fn cancel(_: &Point3f) -> Vector3f {
+            Vector3f::new(0.0, 0.0, 1.0)
+        }
+        let normalv = Vector3f::new(0.0, 0.0, -1.0);
+        assert_eq!(with(cancel).perturb_normal(&p1, &normalv), normalv);
+        assert_float_eq(
+            light_at(with(cancel), p1),
+            light_at(Material::default(), p1),
+        );
+
+        assert_eq!(with(ripples), with(ripples));
+        assert_ne!(with(ripples), with(none));
+        assert_ne!(with(ripples), Material::default());
+        assert_float_ne(with(ripples), with(none));
+        assert_float_ne(with(ripples), Material::default());
+    }
+
+    #[test]
+    fn test_background() {
+        let up = Ray::new(Point3f::new(0.0, 0.0, 0.0), Vector3f::new(0.0, 1.0, 0.0));
+        let down = Ray::new(Point3f::new(0.0, 0.0, 0.0), Vector3f::new(0.0, -2.0, 0.0));
+        let side = Ray::new(Point3f::new(0.0, 0.0, 0.0), Vector3f::new(1.0, 0.0, 0.0));
+
+        let solid = Background::default();
+        assert_eq!(solid, Background::Solid(Color::BLACK));
+        assert_eq!(solid.color_for(&up), Color::BLACK);
+        assert_eq!(solid.color_for(&down), Color::BLACK);
+
+        let sky = Color::new(0.5, 0.7, 1.0);
+        let gradient = Background::Gradient {
+            top: sky,
+            bottom: Color::WHITE,
+        };
+        assert_float_eq(gradient.color_for(&up), sky);
+        assert_float_eq(gradient.color_for(&down), Color::WHITE);
+        assert_float_eq(gradient.color_for(&side), Color::new(0.75, 0.85, 1.0));
+
+        let none = Ray::new(Point3f::new(0.0, 0.0, 0.0), Vector3f::new(0.0, 0.0, 0.0));
+        assert_float_eq(gradient.color_for(&none), Color::new(0.75, 0.85, 1.0));
+    }
+
+    #[test]
+    fn test_fog() {
+        let gray = Color::new(0.5, 0.5, 0.5);
+        let surface = Color::new(1.0, 0.0, 0.0);
+
+        let none = Fog::new(gray, 0.0);
+        assert_eq!(none.apply(surface, 10.0), surface);
+        assert_eq!(none.apply(surface, f64::INFINITY), surface);
+
+        let fog = Fog::new(gray, 0.1);
+        let near = fog.apply(surface, 1.0);
+        let far = fog.apply(surface, 20.0);
+        assert!((near - surface).r().abs() < (far - surface).r().abs());
+        assert!(near.r() > far.r() && far.r() > gray.r());
+        assert_float_eq(fog.apply(surface, 0.0), surface);
+        assert_float_eq(fog.apply(surface, f64::INFINITY), gray);
+        assert_float_eq(
+            fog.apply(surface, 10.0),
+            surface.lerp(&gray, 1.0 - (-1.0_f64).exp()),
+        );
+    }
+
+    #[test]
+    fn test_background_skybox() {
+        // every face is a single color, except for a distinct center pixel
+        let face = |color: Color, center: Color| {
+            Canvas::from_fn(3, 3, |x, y| if (x, y) == (1, 1) { center } else { color })
+        };
+        let red = Color::new(1.0, 0.0, 0.0);
+        let green = Color::new(0.0, 1.0, 0.0);
+        let textures = SkyboxTextures {
+            pos_x: face(red, Color::new(0.5, 0.0, 0.0)),
+            neg_x: face(red, Color::new(0.25, 0.0, 0.0)),
+            pos_y: face(green, Color::new(0.0, 0.5, 0.0)),
+            neg_y: face(green, Color::new(0.0, 0.25, 0.0)),
+            pos_z: face(Color::WHITE, Color::new(0.0, 0.0, 0.5)),
+            neg_z: face(Color::WHITE, Color::new(0.0, 0.0, 0.25)),
+        };
+        let skybox = Background::Skybox(textures.clone());
+        let towards = |x: f64, y: f64, z: f64| {
+            skybox.color_for(&Ray::new(
+                Point3f::new(0.0, 0.0, 0.0),
+                Vector3f::new(x, y, z),
+            ))
+        };
+
+        assert_float_eq(towards(1.0, 0.0, 0.0), textures.pos_x.px(1, 1));
+        assert_float_eq(towards(-3.0, 0.0, 0.0), textures.neg_x.px(1, 1));
+        assert_float_eq(towards(0.0, 1.0, 0.0), textures.pos_y.px(1, 1));
+        assert_float_eq(towards(0.0, -1.0, 0.0), textures.neg_y.px(1, 1));
+        assert_float_eq(towards(0.0, 0.0, 1.0), textures.pos_z.px(1, 1));
+        assert_float_eq(towards(0.0, 0.0, -1.0), textures.neg_z.px(1, 1));
+
+        // mostly +x, but leaning up: top edge of the +x face
+        assert_float_eq(towards(1.0, 0.9, 0.0), red);
+        assert_float_eq(textures.sample(&Vector3f::new(1.0, 0.9, 0.0)), red);
+    }
+
+    #[test]
+    fn test_lighting_blinn_phong() {
+        let phong = Material {
+            shininess: 10.0,
+            ..Default::default()
+        };
+        let blinn_phong = Material {
+            specular_model: SpecularModel::BlinnPhong,
+            ..phong
+        };
+        let light_with = |material: Material, eyev: Vector3f| {
+            lighting(LightingArgs {
+                material,
+                light: PointLight::new(Point3f::new(0.0, 10.0, -10.0), Color::WHITE).into(),
+                point: Point3f::new(0.0, 0.0, 0.0),
+                eyev,
+                normalv: Vector3f::new(0.0, 0.0, -1.0),
+                light_intensity: 1.0,
+            })
+        };
+
+        // eye in the path of the reflection vector, both models give full specular
+        let eyev = Vector3f::new(0.0, -2_f64.sqrt() / 2.0, -2_f64.sqrt() / 2.0);
+        assert_float_eq(light_with(phong, eyev), light_with(blinn_phong, eyev));
+
+        // away from the reflection vector, the blinn-phong highlight falls off slower
+        let eyev = Vector3f::new(0.0, -0.2, -1.0).normalize();
+        let phong_color = light_with(phong, eyev);
+        let blinn_phong_color = light_with(blinn_phong, eyev);
+        assert!(blinn_phong_color.r() > phong_color.r() + 0.1);
+
+        // grazing light with the eye exactly opposite, there is no halfway vector
+        let color = lighting(LightingArgs {
+            material: blinn_phong,
+            light: PointLight::new(Point3f::new(10.0, 0.0, 0.0), Color::WHITE).into(),
+            point: Point3f::new(0.0, 0.0, 0.0),
+            eyev: Vector3f::new(-1.0, 0.0, 0.0),
+            normalv: Vector3f::new(0.0, 0.0, -1.0),
+            light_intensity: 1.0,
+        });
+        assert_float_eq(color, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn test_spotlight_falloff() {
+        let spot = SpotLight::new(
+            Point3f::new(0.0, 0.0, -10.0),
+            Vector3f::new(0.0, 0.0, 1.0),
+            Color::WHITE,
+            10_f64.to_radians(),
+            20_f64.to_radians(),
+        );
+        let point_at_angle = |deg: f64| Point3f::new(10.0 * deg.to_radians().tan(), 0.0, 0.0);
+
+        assert_eq!(spot.falloff(&point_at_angle(0.0)), 1.0);
+        assert_eq!(spot.falloff(&point_at_angle(9.0)), 1.0);
+        assert!((0.0..1.0).contains(&spot.falloff(&point_at_angle(15.0))));
+        assert!(spot.falloff(&point_at_angle(12.0)) > spot.falloff(&point_at_angle(18.0)));
+        assert_eq!(spot.falloff(&point_at_angle(25.0)), 0.0);
+        assert_eq!(spot.falloff(&spot.position), 0.0);
+    }
+
+    #[test]
+    fn test_lighting_spotlight() {
+        let spot = SpotLight::new(
+            Point3f::new(0.0, 0.0, -10.0),
+            Vector3f::new(0.0, 0.0, 1.0),
+            Color::WHITE,
+            10_f64.to_radians(),
+            20_f64.to_radians(),
+        );
+        let point_light = PointLight::new(spot.position, spot.intensity);
+        let light_at = |light: Light, deg: f64| {
+            lighting(LightingArgs {
+                material: Material::default(),
+                light,
+                point: Point3f::new(10.0 * deg.to_radians().tan(), 0.0, 0.0),
+                eyev: Vector3f::new(0.0, 0.0, -1.0),
+                normalv: Vector3f::new(0.0, 0.0, -1.0),
+                light_intensity: 1.0,
+            })
+        };
+
+        assert_float_eq(light_at(spot.into(), 0.0), Color::new(1.9, 1.9, 1.9));
+        assert_float_eq(
+            light_at(spot.into(), 9.0),
+            light_at(point_light.into(), 9.0),
+        );
+
+        let partial = light_at(spot.into(), 15.0);
+        assert!(partial.r() > 0.1 && partial.r() < light_at(point_light.into(), 15.0).r());
+
+        assert_float_eq(light_at(spot.into(), 25.0), Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn test_lighting_at_light_position() {
+        let position = Point3f::new(0.0, 0.0, -10.0);
+        let point_light: Light = PointLight::new(position, Color::WHITE).into();
+        let no_direction: Light =
+            DirectionalLight::new(Vector3f::new(0.0, 0.0, 0.0), Color::WHITE).into();
+
+        [point_light, no_direction].into_iter().for_each(|light| {
+            assert_eq!(light.lightv(&position), Vector3f::new(0.0, 0.0, 0.0));
+
+            let color = lighting(LightingArgs {
+                material: Material::default(),
+                light,
+                point: position,
+                eyev: Vector3f::new(0.0, 0.0, -1.0),
+                normalv: Vector3f::new(0.0, 0.0, -1.0),
+                light_intensity: 1.0,
+            });
+            assert_float_eq(color, Color::new(0.1, 0.1, 0.1));
+        });
+    }
+
+    #[test]
+    fn test_lighting_directional() {
+        let direction = Vector3f::new(1.0, -1.0, 0.0);
+        let directional: Light = DirectionalLight::new(direction, Color::WHITE).into();
+        let far_point: Light =
+            PointLight::new(Point3f::new(0.0, 0.0, 0.0) - direction * 1e6, Color::WHITE).into();
+
+        [
+            Point3f::new(0.0, 0.0, 0.0),
+            Point3f::new(5.0, 0.0, 0.0),
+            Point3f::new(-3.0, 0.0, 8.0),
+        ]
+        .into_iter()
+        .for_each(|point| {
+            let light_with = |light: Light| {
+                lighting(LightingArgs {
+                    material: Material::default(),
+                    light,
+                    point,
+                    eyev: Vector3f::new(0.0, 1.0, 0.0),
+                    normalv: Vector3f::new(0.0, 1.0, 0.0),
+                    light_intensity: 1.0,
+                })
+            };
+
+            let a = light_with(directional);
+            let b = light_with(far_point);
+            assert!((a.r() - b.r()).abs() < 1e-4, "{} != {}", a, b);
+        });
+
+        assert_float_eq(
+            directional.lightv(&Point3f::new(100.0, 0.0, 0.0)),
+            Vector3f::new(-1.0, 1.0, 0.0).normalize(),
+        );
+        assert_eq!(
+            directional.distance(&Point3f::new(0.0, 0.0, 0.0)),
+            f64::INFINITY
+        );
+        assert!(
+            (far_point.distance(&Point3f::new(0.0, 0.0, 0.0)) - 1e6 * 2_f64.sqrt()).abs() < 1e-6
+        );
+    }
 }